@@ -0,0 +1,28 @@
+use demonstrate::demonstrate;
+
+demonstrate! {
+    describe "parsing" {
+        before {
+            let input = vec!["4"; 1024].join(",");
+        }
+
+        bench "sums 1KB of numbers" {
+            input
+                .split(',')
+                .map(|number| number.parse::<u32>().unwrap())
+                .sum::<u32>()
+        }
+
+        describe "nested" {
+            before {
+                let numbers = input.split(',').collect::<Vec<_>>();
+            }
+
+            bench "counts numbers" {
+                numbers.iter().filter(|number| **number == "4").count()
+            }
+        }
+    }
+}
+
+demonstrate::bench_main!();
@@ -12,6 +12,7 @@ demonstrate! {
             let four = 4;
         }
 
+        /// Four is never anything but four
         #[should_panic]
         it "can fail" {
             assert!(four != 4)
@@ -37,6 +38,27 @@ demonstrate! {
                 assert_eq!(four, is_4_task.await)
             }
         }
+
+        #[tag("runtime")]
+        async(tokio, flavor = "multi_thread") context "with tokio" {
+            before_all {
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                let shared_four: u8 = is_4();
+            }
+
+            after_all {
+                assert_eq!(*shared_four, 4)
+            }
+
+            it "sleeps" {
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                assert_eq!(four, *shared_four)
+            }
+
+            async(block_on) it "blocks on" {
+                assert_eq!(four, async { is_4() }.await)
+            }
+        }
     }
 }
 
@@ -0,0 +1,1115 @@
+//! Defines the various blocks used by the `demonstrate!` macro and their corresponding `Parse`
+//! implementations.
+
+use crate::fixture::FixtureDef;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use syn::parse::{Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{
+    braced, parenthesized, parse_quote, token, AttrStyle, Attribute, Expr, Ident, LitInt, LitStr,
+    Meta, Pat, Path, Stmt, Token, Type, UseTree,
+};
+
+/// Custom keywords used for the new blocks available in the `demonstrate!` macro
+mod keyword {
+    use syn::custom_keyword;
+
+    custom_keyword!(before);
+
+    custom_keyword!(after);
+
+    custom_keyword!(before_all);
+
+    custom_keyword!(after_all);
+
+    // Are aliases for eachother:
+    custom_keyword!(describe);
+    custom_keyword!(context);
+    custom_keyword!(given);
+    custom_keyword!(when);
+
+    // Are aliases for eachother:
+    custom_keyword!(it);
+    custom_keyword!(test);
+    custom_keyword!(then);
+
+    custom_keyword!(bench);
+    // Generates a benchmark for the libtest harness on nightly
+    custom_keyword!(nightly);
+
+    // Declares a resource, along with the size of its pool and how it's torn down:
+    custom_keyword!(resource);
+    custom_keyword!(pool);
+    custom_keyword!(teardown);
+
+    // Declares a fixture, which can be scoped to a `describe` block instead of a test:
+    custom_keyword!(fixture);
+
+    // Declares the fields of the context passed to tests:
+    custom_keyword!(state);
+
+    // Runtimes available to `async` blocks:
+    custom_keyword!(tokio);
+    custom_keyword!(async_std);
+    custom_keyword!(block_on);
+
+    // Skips tests at runtime unless an environment variable is set:
+    custom_keyword!(skip_unless);
+    custom_keyword!(env);
+
+    // Set the process state while tests run:
+    custom_keyword!(with_env);
+    custom_keyword!(in_dir);
+    custom_keyword!(in_tempdir);
+
+    // The outcomes expected of tests:
+    custom_keyword!(panics_with);
+    custom_keyword!(errs_with);
+    custom_keyword!(exits_with);
+    custom_keyword!(aborts);
+    custom_keyword!(stderr_contains);
+
+    // Marks tests as known to fail:
+    custom_keyword!(xfail);
+
+    // Declares a test whose contents are expected not to compile:
+    custom_keyword!(compile_fail);
+
+    // Arguments for the `tokio` runtime:
+    custom_keyword!(flavor);
+    custom_keyword!(worker_threads);
+}
+
+/// All the root `Describe` blocks defined in the current `demonstrate!` instance
+pub(crate) struct Root(pub(crate) Vec<Describe>);
+
+impl Parse for Root {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut blocks = Vec::new();
+        while !input.is_empty() {
+            blocks.push(input.parse::<Describe>()?);
+        }
+
+        Ok(Root(blocks))
+    }
+}
+
+/// The block types that can exist in a `Describe` block with corresponding `BlockProps`
+pub(crate) enum Block {
+    Describe(Box<Describe>),
+    Test(Box<Test>),
+    CompileFail(CompileFail),
+    Bench(Bench),
+}
+
+impl Parse for Block {
+    fn parse(input: ParseStream) -> Result<Self> {
+        // Create forked stream for determining block type, passing the original input stream to
+        // the block's respective parse function
+        let fork = input.fork();
+
+        // These properties are parsed in the `Parse` implementation for `BlockProps`
+        let _attibutes = fork.call(Attribute::parse_outer)?;
+        let _async = parse_async(&fork)?;
+
+        let lookahead = fork.lookahead1();
+        if lookahead.peek(keyword::it)
+            || lookahead.peek(keyword::test)
+            || lookahead.peek(keyword::then)
+        {
+            // `compile_fail` follows the name of a test, so the test's properties are parsed first
+            let fork = input.fork();
+            if fork.parse::<BlockProps>().is_ok() && fork.peek(keyword::compile_fail) {
+                Ok(Block::CompileFail(input.parse::<CompileFail>()?))
+            } else {
+                Ok(Block::Test(Box::new(input.parse::<Test>()?)))
+            }
+        } else if lookahead.peek(keyword::describe)
+            || lookahead.peek(keyword::context)
+            || lookahead.peek(keyword::given)
+            || lookahead.peek(keyword::when)
+        {
+            Ok(Block::Describe(Box::new(input.parse::<Describe>()?)))
+        } else if lookahead.peek(keyword::bench) {
+            Ok(Block::Bench(input.parse::<Bench>()?))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+/// The `describe`/`context` block type
+pub(crate) struct Describe {
+    /// The properties that are either parsed or inherited by ancestoral Describe blocks
+    pub(crate) properties: DescribeProps,
+    /// The nested `Describe` blocks and contained `Test` blocks for this block instance
+    pub(crate) blocks: Vec<Block>,
+    /// The `before_all` block for this block instance, which is not inherited
+    pub(crate) before_all: Option<BasicBlock>,
+    /// The `after_all` block for this block instance, which is not inherited
+    pub(crate) after_all: Option<BasicBlock>,
+    /// The resources declared by this block instance, which tests anywhere can use
+    pub(crate) resources: Vec<Resource>,
+    /// The fixtures declared by this block instance, which descendant tests can request
+    pub(crate) fixtures: Vec<Fixture>,
+    /// The attributes of the generated module rather than its tests, which are the block's doc
+    /// comments, its inner attributes and those declared with `#[module(..)]`
+    pub(crate) attributes: Vec<Attribute>,
+}
+
+impl Parse for Describe {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut block_props = input.parse::<BlockProps>()?;
+        let mut attributes = module_attributes(&mut block_props.attributes)?;
+
+        let content;
+        braced!(content in input);
+
+        // Inner attributes apply to the generated module, as they would to a `mod` block
+        attributes.extend(content.call(Attribute::parse_inner)?.into_iter().map(
+            |mut attribute| {
+                attribute.style = AttrStyle::Outer;
+                attribute
+            },
+        ));
+
+        let mut uses = Vec::new();
+        let mut before = None;
+        let mut after = None;
+        let mut before_all = None;
+        let mut after_all = None;
+        let mut resources = Vec::new();
+        let mut fixtures = Vec::new();
+        let mut state = None;
+        let mut blocks = Vec::new();
+
+        while !content.is_empty() {
+            while content.parse::<Option<Token![use]>>()?.is_some() {
+                uses.push(content.parse::<UseTree>()?);
+                content.parse::<Token![;]>()?;
+            }
+
+            let block = content.parse::<DescribeBlock>()?;
+            match block {
+                DescribeBlock::Before(BasicBlock(block)) => {
+                    if before.is_none() {
+                        before = Some(BasicBlock(block));
+                    } else {
+                        return Err(
+                            content.error("Only one `before` statement per describe/context block")
+                        );
+                    }
+                }
+                DescribeBlock::After(BasicBlock(block)) => {
+                    if after.is_none() {
+                        after = Some(BasicBlock(block));
+                    } else {
+                        return Err(
+                            content.error("Only one `after` statement per describe/context block")
+                        );
+                    }
+                }
+                DescribeBlock::BeforeAll(BasicBlock(block)) => {
+                    if before_all.is_none() {
+                        before_all = Some(BasicBlock(block));
+                    } else {
+                        return Err(content
+                            .error("Only one `before_all` statement per describe/context block"));
+                    }
+                }
+                DescribeBlock::AfterAll(BasicBlock(block)) => {
+                    if after_all.is_none() {
+                        after_all = Some(BasicBlock(block));
+                    } else {
+                        return Err(content
+                            .error("Only one `after_all` statement per describe/context block"));
+                    }
+                }
+                DescribeBlock::Resource(resource) => resources.push(*resource),
+                DescribeBlock::Fixture(fixture) => fixtures.push(*fixture),
+                DescribeBlock::State(fields) => {
+                    if state.is_none() {
+                        state = Some(fields);
+                    } else {
+                        return Err(
+                            content.error("Only one `state` statement per describe/context block")
+                        );
+                    }
+                }
+                DescribeBlock::Regular(block) => blocks.push(*block),
+            }
+        }
+
+        Ok(Describe {
+            properties: DescribeProps {
+                block_props,
+                uses,
+                before,
+                after,
+                shared: Vec::new(),
+                fixtures: Vec::new(),
+                state: state.unwrap_or_default(),
+            },
+            blocks,
+            before_all,
+            after_all,
+            resources,
+            fixtures,
+            attributes,
+        })
+    }
+}
+
+/// Removes the attributes that apply to the module generated for a `Describe` block from those
+/// inherited by its tests, unwrapping those declared with `#[module(..)]`
+fn module_attributes(attributes: &mut Vec<Attribute>) -> Result<Vec<Attribute>> {
+    let is_module_attribute =
+        |attribute: &Attribute| attribute.path.is_ident("doc") || attribute.path.is_ident("module");
+    let (module_attributes, test_attributes): (Vec<_>, Vec<_>) =
+        attributes.drain(..).partition(is_module_attribute);
+    *attributes = test_attributes;
+
+    let mut unwrapped = Vec::new();
+    for attribute in module_attributes {
+        if attribute.path.is_ident("doc") {
+            unwrapped.push(attribute);
+            continue;
+        }
+
+        let metas = attribute.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        unwrapped.extend(
+            metas
+                .into_iter()
+                .map(|meta| -> Attribute { parse_quote!(#[#meta]) }),
+        );
+    }
+
+    Ok(unwrapped)
+}
+
+/// Properties for `Describe` blocks that will be inherited and passed down to nested blocks
+#[derive(Clone)]
+pub(crate) struct DescribeProps {
+    /// The properties that will be passed to all descending tests
+    pub(crate) block_props: BlockProps,
+    /// The paths declared with `use` tokens within this block instance
+    pub(crate) uses: Vec<UseTree>,
+    /// The `before` block for this block instance
+    pub(crate) before: Option<BasicBlock>,
+    /// The `after` block for this block instance
+    pub(crate) after: Option<BasicBlock>,
+    /// The state shared by this block instance and its ancestors' `before_all` blocks
+    pub(crate) shared: Vec<SharedState>,
+    /// The fixtures declared by this block instance and its ancestors
+    pub(crate) fixtures: Vec<FixtureDef>,
+    /// The fields of the context declared by the `state` blocks of this block instance and its
+    /// ancestors, which is passed to tests as `ctx`
+    pub(crate) state: Vec<StateField>,
+}
+
+/// A field declared as `name: Type = default` by a `state` block
+#[derive(Clone)]
+pub(crate) struct StateField {
+    pub(crate) name: Ident,
+    pub(crate) ty: Type,
+    /// The expression that initializes the field for each test
+    pub(crate) default: Expr,
+}
+
+impl Parse for StateField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse::<Ident>()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse::<Type>()?;
+        input.parse::<Token![=]>()?;
+
+        Ok(StateField {
+            name,
+            ty,
+            default: input.parse::<Expr>()?,
+        })
+    }
+}
+
+/// The state set up by a `before_all` block and leased by each descendant test
+#[derive(Clone)]
+pub(crate) struct SharedState {
+    /// The generated function that leases this state
+    pub(crate) lease: Ident,
+    /// The `before_all` bindings that are shared with descendant tests
+    pub(crate) fields: Vec<Ident>,
+}
+
+/// All the blocks permitted within a `Describe` block
+enum DescribeBlock {
+    /// A nested `Describe` or `Test` block
+    Regular(Box<Block>),
+    /// A `before {}` block
+    Before(BasicBlock),
+    /// An `after {}` block
+    After(BasicBlock),
+    /// A `before_all {}` block
+    BeforeAll(BasicBlock),
+    /// An `after_all {}` block
+    AfterAll(BasicBlock),
+    /// A `resource "" -> Type {} teardown {}` block
+    Resource(Box<Resource>),
+    /// A `fixture name() -> Type {} teardown {}` block
+    Fixture(Box<Fixture>),
+    /// A `state { name: Type = default }` block
+    State(Vec<StateField>),
+}
+
+impl Parse for DescribeBlock {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.parse::<Option<keyword::before>>()?.is_some() {
+            Ok(DescribeBlock::Before(input.parse::<BasicBlock>()?))
+        } else if input.parse::<Option<keyword::after>>()?.is_some() {
+            Ok(DescribeBlock::After(input.parse::<BasicBlock>()?))
+        } else if input.parse::<Option<keyword::before_all>>()?.is_some() {
+            Ok(DescribeBlock::BeforeAll(input.parse::<BasicBlock>()?))
+        } else if input.parse::<Option<keyword::after_all>>()?.is_some() {
+            Ok(DescribeBlock::AfterAll(input.parse::<BasicBlock>()?))
+        } else if input.peek(keyword::resource) {
+            Ok(DescribeBlock::Resource(Box::new(
+                input.parse::<Resource>()?,
+            )))
+        } else if input.parse::<Option<keyword::state>>()?.is_some() {
+            let content;
+            braced!(content in input);
+            let fields = content.parse_terminated::<_, Token![,]>(StateField::parse)?;
+            Ok(DescribeBlock::State(fields.into_iter().collect()))
+        } else if input.peek(keyword::fixture) {
+            Ok(DescribeBlock::Fixture(Box::new(input.parse::<Fixture>()?)))
+        } else {
+            Ok(DescribeBlock::Regular(Box::new(input.parse::<Block>()?)))
+        }
+    }
+}
+
+/// A `resource` block, which is set up for the first test to use it and torn down once the last
+/// test using it has finished
+pub(crate) struct Resource {
+    /// The name of the resource, which is also the name of the generated function and of the
+    /// bindings of its instances
+    pub(crate) name: LitStr,
+    /// The number of instances declared with `resource(pool = N)`, each of which is leased by one
+    /// test at a time, or `None` if a single instance is shared by every test
+    pub(crate) pool: Option<LitInt>,
+    /// The type of the resource's instances
+    pub(crate) ty: Type,
+    /// The code sequence that sets up an instance
+    pub(crate) setup: BasicBlock,
+    /// The code sequence that tears an instance down
+    pub(crate) teardown: Option<BasicBlock>,
+}
+
+impl Parse for Resource {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<keyword::resource>()?;
+        let pool = if input.peek(token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            content.parse::<keyword::pool>()?;
+            content.parse::<Token![=]>()?;
+            Some(content.parse::<LitInt>()?)
+        } else {
+            None
+        };
+
+        let name = input.parse::<LitStr>()?;
+        if input.parse::<Option<Token![->]>>()?.is_none() {
+            return Err(
+                input.error("Expected the type of the resource, as in `resource \"..\" -> Type`")
+            );
+        }
+        let ty = input.parse::<Type>()?;
+        let setup = input.parse::<BasicBlock>()?;
+        let teardown = if input.parse::<Option<keyword::teardown>>()?.is_some() {
+            Some(input.parse::<BasicBlock>()?)
+        } else {
+            None
+        };
+
+        Ok(Resource {
+            name,
+            pool,
+            ty,
+            setup,
+            teardown,
+        })
+    }
+}
+
+/// A `fixture` block, which is set up for each test that requests it, directly or through another
+/// fixture, and torn down once the test has finished
+pub(crate) struct Fixture {
+    /// Whether a single instance is shared by the tests of the `describe`/`context` block
+    /// declaring the fixture, as declared with `fixture(describe)`, rather than set up per test
+    pub(crate) scope: Scope,
+    /// The name that tests request the fixture by
+    pub(crate) name: Ident,
+    /// The other fixtures that this fixture is set up from
+    pub(crate) dependencies: Vec<FixtureRequest>,
+    /// The type of the fixture
+    pub(crate) ty: Type,
+    /// The code sequence that sets up the fixture
+    pub(crate) setup: BasicBlock,
+    /// The code sequence that tears the fixture down, before those of its dependencies
+    pub(crate) teardown: Option<BasicBlock>,
+}
+
+/// The scope of a fixture's instances
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scope {
+    Test,
+    Describe,
+}
+
+impl Parse for Fixture {
+    fn parse(input: ParseStream) -> Result<Self> {
+        input.parse::<keyword::fixture>()?;
+        let scope = if input.peek(token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            let lookahead = content.lookahead1();
+            if lookahead.peek(keyword::describe) {
+                content.parse::<keyword::describe>()?;
+                Scope::Describe
+            } else if lookahead.peek(keyword::test) {
+                content.parse::<keyword::test>()?;
+                Scope::Test
+            } else {
+                return Err(lookahead.error());
+            }
+        } else {
+            Scope::Test
+        };
+
+        let name = input.parse::<Ident>()?;
+        let dependencies = FixtureRequest::parse_list(input)?;
+        if input.parse::<Option<Token![->]>>()?.is_none() {
+            return Err(
+                input.error("Expected the type of the fixture, as in `fixture name() -> Type`")
+            );
+        }
+        let ty = input.parse::<Type>()?;
+        let setup = input.parse::<BasicBlock>()?;
+        let teardown = if input.parse::<Option<keyword::teardown>>()?.is_some() {
+            Some(input.parse::<BasicBlock>()?)
+        } else {
+            None
+        };
+
+        Ok(Fixture {
+            scope,
+            name,
+            dependencies,
+            ty,
+            setup,
+            teardown,
+        })
+    }
+}
+
+/// A fixture requested as `binding: fixture` by a block or another fixture
+#[derive(Clone)]
+pub(crate) struct FixtureRequest {
+    /// The name that the fixture is bound to
+    pub(crate) binding: Ident,
+    /// The name of the requested fixture
+    pub(crate) fixture: Ident,
+}
+
+impl FixtureRequest {
+    /// Parses an optional parenthesized list of requests
+    fn parse_list(input: ParseStream) -> Result<Vec<Self>> {
+        if !input.peek(token::Paren) {
+            return Ok(Vec::new());
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let requests = content.parse_terminated::<_, Token![,]>(|input| {
+            let binding = input.parse::<Ident>()?;
+            input.parse::<Token![:]>()?;
+            Ok(FixtureRequest {
+                binding,
+                fixture: input.parse::<Ident>()?,
+            })
+        })?;
+
+        Ok(requests.into_iter().collect())
+    }
+}
+
+/// An `it`/`test` block
+pub(crate) struct Test {
+    /// The properties defined for this test, or inherited from ancestoral `Describe` blocks
+    pub(crate) properties: BlockProps,
+    /// The outcome that this test expects of its unique contents, if it doesn't expect them to
+    /// succeed
+    pub(crate) expectation: Option<Expectation>,
+    /// The unique contents of this test
+    pub(crate) content: BasicBlock,
+    /// The statements leasing the shared state and describe-scoped fixtures that the test uses,
+    /// which precede anything that can skip it so that they're counted as finished either way
+    pub(crate) leases: Vec<Stmt>,
+    /// The functions returning the shared state that the test leases, which it's registered
+    /// with so that the state counts the tests that `#[cfg]` keeps
+    pub(crate) shared: Vec<Ident>,
+    /// The statements setting up the shared state and context used by the `before` code
+    /// sequences, which the leases and preamble of the test are later prepended to
+    pub(crate) setup: Vec<Stmt>,
+    /// The inherited `before` code sequences, which are run between `setup` and `content`
+    pub(crate) before: Vec<Stmt>,
+    /// The inherited `after` code sequences followed by the check of the test's expectation,
+    /// which are run after `content`
+    pub(crate) after: Vec<Stmt>,
+}
+
+impl Parse for Test {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(Test {
+            properties: input.parse::<BlockProps>()?,
+            expectation: Expectation::parse_optional(input)?,
+            content: input.parse::<BasicBlock>()?,
+            leases: Vec::new(),
+            shared: Vec::new(),
+            setup: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        })
+    }
+}
+
+/// The outcome expected of a test, declared after its name and modifiers
+pub(crate) enum Expectation {
+    /// `panics_with ".."`, expecting a panic whose message contains the string
+    Panic(LitStr),
+    /// `errs_with Pattern`, expecting the test to return an `Err` whose error matches the pattern
+    Error(Pat),
+    /// `exits_with N`, `aborts` or `stderr_contains ".."`, expecting the test to end the process
+    /// that it's run in
+    Death(Death),
+}
+
+/// How a death test is expected to end the child process that it's run in
+pub(crate) struct Death {
+    pub(crate) exit: Exit,
+    /// The strings declared with `stderr_contains ".."`, which the process' stderr must contain
+    pub(crate) stderr: Vec<LitStr>,
+}
+
+/// The way that a death test's process is expected to exit
+pub(crate) enum Exit {
+    /// `exits_with N`, exiting with the status code
+    Code(LitInt),
+    /// `aborts`, aborting the process
+    Abort,
+    /// Exiting in any way, when only `stderr_contains ".."` was declared
+    Any,
+}
+
+impl Expectation {
+    fn parse_optional(input: ParseStream) -> Result<Option<Self>> {
+        if input.parse::<Option<keyword::panics_with>>()?.is_some() {
+            Ok(Some(Expectation::Panic(input.parse::<LitStr>()?)))
+        } else if input.parse::<Option<keyword::errs_with>>()?.is_some() {
+            // The pattern extends up to the test's braces, so struct patterns must be parenthesized
+            let mut pattern = TokenStream::new();
+            while !input.is_empty() && !input.peek(token::Brace) {
+                pattern.extend(Some(input.parse::<TokenTree>()?));
+            }
+            Ok(Some(Expectation::Error(syn::parse2::<Pat>(pattern)?)))
+        } else {
+            Ok(Death::parse_optional(input)?.map(Expectation::Death))
+        }
+    }
+}
+
+impl Death {
+    fn parse_optional(input: ParseStream) -> Result<Option<Self>> {
+        let mut death = Death {
+            exit: Exit::Any,
+            stderr: Vec::new(),
+        };
+        let mut declared = false;
+        loop {
+            let span = input.span();
+            let exit = if input.parse::<Option<keyword::exits_with>>()?.is_some() {
+                let code = input.parse::<LitInt>()?;
+                code.base10_parse::<i32>()?;
+                Exit::Code(code)
+            } else if input.parse::<Option<keyword::aborts>>()?.is_some() {
+                Exit::Abort
+            } else if input.parse::<Option<keyword::stderr_contains>>()?.is_some() {
+                death.stderr.push(input.parse::<LitStr>()?);
+                declared = true;
+                continue;
+            } else {
+                break;
+            };
+
+            if !matches!(death.exit, Exit::Any) {
+                return Err(syn::Error::new(
+                    span,
+                    "Only one of `exits_with` and `aborts` can be declared",
+                ));
+            }
+            death.exit = exit;
+            declared = true;
+        }
+
+        Ok(Some(death).filter(|_| declared))
+    }
+}
+
+/// An `it`/`test` block declared with `compile_fail`, which is generated as a doctest
+pub(crate) struct CompileFail {
+    /// The properties defined for this test, of which only its name and tags are used
+    pub(crate) properties: BlockProps,
+    /// The error code that compiling the snippet is expected to fail with, such as `E0505`
+    pub(crate) code: Option<Ident>,
+    /// The snippet that is expected not to compile
+    pub(crate) content: TokenStream,
+}
+
+impl Parse for CompileFail {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let properties = input.parse::<BlockProps>()?;
+        input.parse::<keyword::compile_fail>()?;
+
+        // The snippet is compiled on its own, so the test can't declare anything that would be
+        // generated along with it
+        let BlockProps {
+            attributes,
+            is_async,
+            return_type,
+            required_env,
+            xfail,
+            serial,
+            resources,
+            env,
+            dirs,
+            fixtures,
+            span,
+            ..
+        } = &properties;
+        if *is_async
+            || return_type.is_some()
+            || !required_env.is_empty()
+            || xfail.is_some()
+            || !serial.is_empty()
+            || !resources.is_empty()
+            || !env.is_empty()
+            || !dirs.is_empty()
+            || !fixtures.is_empty()
+            || attributes
+                .iter()
+                .any(|attribute| !attribute.path.is_ident("doc"))
+        {
+            return Err(syn::Error::new(
+                *span,
+                "A `compile_fail` test is compiled on its own as a doctest, so it can only declare \
+                 its name, tags, doc comments and the error code it expects",
+            ));
+        }
+
+        let code = if input.peek(token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            let code = content.parse::<Ident>()?;
+            let digits = code.to_string();
+            let digits = digits.strip_prefix('E').unwrap_or_default();
+            if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(syn::Error::new(
+                    code.span(),
+                    "Expected an error code such as `E0505`",
+                ));
+            }
+            Some(code)
+        } else {
+            None
+        };
+
+        let content;
+        braced!(content in input);
+
+        Ok(CompileFail {
+            properties,
+            code,
+            content: content.parse::<TokenStream>()?,
+        })
+    }
+}
+
+/// A `bench` block
+pub(crate) struct Bench {
+    /// The properties defined for this benchmark, or inherited from ancestoral `Describe` blocks
+    pub(crate) properties: BlockProps,
+    /// Whether this benchmark was declared as `bench(nightly)`, generating a `#[bench]` function
+    /// for the libtest harness instead of one for `demonstrate::bench_main!()`
+    pub(crate) nightly: bool,
+    /// The `before` code sequences inherited from ancestoral `Describe` blocks, which are
+    /// executed outside of the timed region
+    pub(crate) before: Vec<Stmt>,
+    /// The code sequence that is timed
+    pub(crate) content: BasicBlock,
+    /// The `after` code sequences inherited from ancestoral `Describe` blocks, which are
+    /// executed outside of the timed region
+    pub(crate) after: Vec<Stmt>,
+}
+
+impl Parse for Bench {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attributes = input.call(Attribute::parse_outer)?;
+        let (is_async, runtime) = parse_async(input)?;
+        input.parse::<keyword::bench>()?;
+        let nightly = if input.peek(token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            content.parse::<keyword::nightly>()?;
+            true
+        } else {
+            false
+        };
+
+        Ok(Bench {
+            properties: BlockProps::parse_named(input, attributes, is_async, runtime)?,
+            nightly,
+            before: Vec::new(),
+            content: input.parse::<BasicBlock>()?,
+            after: Vec::new(),
+        })
+    }
+}
+
+/// Simply lines of source code that were originally within curly braces
+#[derive(Clone)]
+pub(crate) struct BasicBlock(pub(crate) Vec<Stmt>);
+
+impl Parse for BasicBlock {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let content;
+        braced!(content in input);
+
+        Ok(BasicBlock(content.call(syn::Block::parse_within)?))
+    }
+}
+
+/// Properties that can apply to `Describe` and `Test` blocks
+#[derive(Clone)]
+pub(crate) struct BlockProps {
+    /// Outer attributes, preceded by those inherited from ancestors
+    pub(crate) attributes: Vec<Attribute>,
+    /// The attributes that this block doesn't inherit from its ancestors
+    pub(crate) no_inherit: NoInherit,
+    /// The tags declared with `#[tag(..)]` for this block or an ancestor
+    pub(crate) tags: Vec<LitStr>,
+    /// Whether this block or an ancestor was declared as `async`
+    pub(crate) is_async: bool,
+    /// The runtime that was either defined for this block or an ancestor (if one was not
+    /// specified)
+    pub(crate) runtime: Option<Runtime>,
+    /// The unique name for this block
+    pub(crate) name: String,
+    /// The names of this block's ancestors followed by its own
+    pub(crate) path: Vec<String>,
+    /// The span of this block's name, used for reporting errors
+    pub(crate) span: Span,
+    /// The return type that was either defined for this block or an ancestor (if one was not
+    /// specified)
+    pub(crate) return_type: Option<Type>,
+    /// The environment variables declared with `skip_unless env ".."` for this block or an
+    /// ancestor, without which its tests are skipped
+    pub(crate) required_env: Vec<LitStr>,
+    /// The reason declared with `xfail ".."` for this block or an ancestor (if one was not
+    /// specified), for which its tests are expected to fail
+    pub(crate) xfail: Option<LitStr>,
+    /// The keys declared with `#[serial]` or `#[serial(..)]` for this block or an ancestor, whose
+    /// locks are held by its tests while they run
+    pub(crate) serial: Vec<String>,
+    /// The paths to the resources declared with `#[resource(..)]` for this block or an ancestor,
+    /// which are leased by its tests while they run
+    pub(crate) resources: Vec<Path>,
+    /// The environment variables declared with `with_env { ".." => .. }` for this block or an
+    /// ancestor, which are set while its tests run
+    pub(crate) env: Vec<(LitStr, Expr)>,
+    /// The working directories declared with `in_dir(..)` or `in_tempdir` for this block or an
+    /// ancestor, which are changed into in order while its tests run
+    pub(crate) dirs: Vec<Dir>,
+    /// The fixtures requested as `(binding: fixture, ..)` by this block or an ancestor
+    pub(crate) fixtures: Vec<FixtureRequest>,
+}
+
+/// The attributes that a block doesn't inherit, declared with `#[no_inherit]` or
+/// `#[no_inherit(..)]`
+#[derive(Clone)]
+pub(crate) enum NoInherit {
+    /// None of the attributes are inherited
+    All,
+    /// The attributes with the given paths aren't inherited
+    Paths(Vec<Path>),
+}
+
+/// A working directory declared by a block
+#[derive(Clone)]
+pub(crate) enum Dir {
+    /// An `in_dir(..)` directory
+    Path(Box<Expr>),
+    /// An `in_tempdir` directory, which is created for each test and removed afterwards
+    Temp,
+}
+
+impl Parse for BlockProps {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let attributes = input.call(Attribute::parse_outer)?;
+        let (is_async, runtime) = parse_async(input)?;
+        // The block type keyword is parsed in the `Parse` implementation for `Block`
+        let _block_type = input.parse::<Ident>()?;
+
+        BlockProps::parse_named(input, attributes, is_async, runtime)
+    }
+}
+
+impl BlockProps {
+    /// Parses the name, requested fixtures, optional return type, `skip_unless` conditions,
+    /// `xfail` reason and process state modifiers that follow the block type keyword
+    fn parse_named(
+        input: ParseStream,
+        attributes: Vec<Attribute>,
+        is_async: bool,
+        runtime: Option<Runtime>,
+    ) -> Result<Self> {
+        // `#[tag(..)]`, `#[serial]` and `#[resource(..)]` attributes are consumed by the macro
+        // rather than generated
+        let (tag_attributes, attributes): (Vec<_>, Vec<_>) = attributes
+            .into_iter()
+            .partition(|attribute| attribute.path.is_ident("tag"));
+        let mut tags = Vec::new();
+        for attribute in tag_attributes {
+            tags.extend(
+                attribute.parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)?,
+            );
+        }
+
+        let (serial_attributes, attributes): (Vec<_>, Vec<_>) = attributes
+            .into_iter()
+            .partition(|attribute| attribute.path.is_ident("serial"));
+        let mut serial = Vec::new();
+        for attribute in serial_attributes {
+            // `#[serial]` uses a single key shared by every test marked without one
+            if attribute.tokens.is_empty() {
+                serial.push(String::new());
+                continue;
+            }
+            let keys =
+                attribute.parse_args_with(Punctuated::<SerialKey, Token![,]>::parse_terminated)?;
+            serial.extend(keys.into_iter().map(|SerialKey(key)| key));
+        }
+
+        let (resource_attributes, attributes): (Vec<_>, Vec<_>) = attributes
+            .into_iter()
+            .partition(|attribute| attribute.path.is_ident("resource"));
+        let mut resources = Vec::new();
+        for attribute in resource_attributes {
+            resources.extend(
+                attribute.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)?,
+            );
+        }
+
+        // `#[no_inherit]` keeps the block from inheriting any attributes, while `#[no_inherit(..)]`
+        // lists those it doesn't inherit
+        let (no_inherit_attributes, attributes): (Vec<_>, Vec<_>) = attributes
+            .into_iter()
+            .partition(|attribute| attribute.path.is_ident("no_inherit"));
+        let mut no_inherit = NoInherit::Paths(Vec::new());
+        for attribute in no_inherit_attributes {
+            if attribute.tokens.is_empty() {
+                no_inherit = NoInherit::All;
+            } else if let NoInherit::Paths(paths) = &mut no_inherit {
+                paths.extend(
+                    attribute.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)?,
+                );
+            }
+        }
+
+        let name = input.parse::<LitStr>()?;
+        let span = name.span();
+        let name = name.value();
+        let fixtures = FixtureRequest::parse_list(input)?;
+        let return_type = if input.parse::<Option<Token![->]>>()?.is_some() {
+            Some(input.parse::<Type>()?)
+        } else {
+            None
+        };
+
+        let mut required_env = Vec::new();
+        let mut xfail = None;
+        let mut env = Vec::new();
+        let mut dirs = Vec::new();
+        loop {
+            if input.parse::<Option<keyword::skip_unless>>()?.is_some() {
+                input.parse::<keyword::env>()?;
+                required_env.push(input.parse::<LitStr>()?);
+            } else if input.parse::<Option<keyword::xfail>>()?.is_some() {
+                xfail = Some(input.parse::<LitStr>()?);
+            } else if input.parse::<Option<keyword::with_env>>()?.is_some() {
+                let content;
+                braced!(content in input);
+                let variables = content.parse_terminated::<_, Token![,]>(|input| {
+                    let variable = input.parse::<LitStr>()?;
+                    input.parse::<Token![=>]>()?;
+                    Ok((variable, input.parse::<Expr>()?))
+                })?;
+                env.extend(variables);
+            } else if input.parse::<Option<keyword::in_dir>>()?.is_some() {
+                let content;
+                parenthesized!(content in input);
+                dirs.push(Dir::Path(Box::new(content.parse::<Expr>()?)));
+            } else if input.parse::<Option<keyword::in_tempdir>>()?.is_some() {
+                dirs.push(Dir::Temp);
+            } else {
+                break;
+            }
+        }
+
+        // Tests that set the process state are kept from running concurrently with each other
+        if !env.is_empty() {
+            serial.push("env".to_owned());
+        }
+        if !dirs.is_empty() {
+            serial.push("cwd".to_owned());
+        }
+        serial.sort();
+        serial.dedup();
+
+        Ok(BlockProps {
+            attributes,
+            no_inherit,
+            tags,
+            is_async,
+            runtime,
+            path: vec![name.clone()],
+            name,
+            span,
+            return_type,
+            required_env,
+            xfail,
+            serial,
+            resources,
+            env,
+            dirs,
+            fixtures,
+        })
+    }
+}
+
+/// A key given to `#[serial(..)]`, as either an identifier or a string literal
+struct SerialKey(String);
+
+impl Parse for SerialKey {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(LitStr) {
+            Ok(SerialKey(input.parse::<LitStr>()?.value()))
+        } else if lookahead.peek(Ident) {
+            Ok(SerialKey(input.parse::<Ident>()?.to_string()))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+/// Parses an optional `async` token followed by an optional parenthesized `Runtime`
+fn parse_async(input: ParseStream) -> Result<(bool, Option<Runtime>)> {
+    if input.parse::<Option<Token![async]>>()?.is_none() {
+        return Ok((false, None));
+    }
+
+    let runtime = if input.peek(token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        Some(content.parse::<Runtime>()?)
+    } else {
+        None
+    };
+
+    Ok((true, runtime))
+}
+
+/// The executor that drives an `async` test to completion, selected with `async(..)`
+#[derive(Clone)]
+pub(crate) enum Runtime {
+    /// `async(tokio)`, optionally followed by `flavor = ".."` and `worker_threads = ..`
+    Tokio {
+        /// Whether the `"multi_thread"` flavor was selected over `"current_thread"`
+        multi_thread: bool,
+        /// The number of worker threads for the `"multi_thread"` flavor
+        worker_threads: Option<LitInt>,
+    },
+    /// `async(async_std)`
+    AsyncStd,
+    /// `async(block_on)`, the dependency-free executor provided by `demonstrate::block_on`
+    BlockOn,
+}
+
+impl Parse for Runtime {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let lookahead = input.lookahead1();
+        let runtime = if lookahead.peek(keyword::tokio) {
+            input.parse::<keyword::tokio>()?;
+
+            let mut multi_thread = false;
+            let mut worker_threads = None;
+            while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+                if input.parse::<Option<keyword::flavor>>()?.is_some() {
+                    input.parse::<Token![=]>()?;
+                    let flavor = input.parse::<LitStr>()?;
+                    multi_thread = match flavor.value().as_str() {
+                        "current_thread" => false,
+                        "multi_thread" => true,
+                        _ => {
+                            return Err(syn::Error::new(
+                                flavor.span(),
+                                "Expected a flavor of \"current_thread\" or \"multi_thread\"",
+                            ))
+                        }
+                    };
+                } else if input.parse::<Option<keyword::worker_threads>>()?.is_some() {
+                    input.parse::<Token![=]>()?;
+                    worker_threads = Some(input.parse::<LitInt>()?);
+                } else {
+                    return Err(input.error("Expected `flavor` or `worker_threads`"));
+                }
+            }
+
+            if let (false, Some(worker_threads)) = (multi_thread, &worker_threads) {
+                return Err(syn::Error::new(
+                    worker_threads.span(),
+                    "`worker_threads` requires `flavor = \"multi_thread\"`",
+                ));
+            }
+
+            Runtime::Tokio {
+                multi_thread,
+                worker_threads,
+            }
+        } else if lookahead.peek(keyword::async_std) {
+            input.parse::<keyword::async_std>()?;
+            Runtime::AsyncStd
+        } else if lookahead.peek(keyword::block_on) {
+            input.parse::<keyword::block_on>()?;
+            Runtime::BlockOn
+        } else {
+            return Err(lookahead.error());
+        };
+
+        if !input.is_empty() {
+            return Err(input.error("Unexpected runtime argument"));
+        }
+
+        Ok(runtime)
+    }
+}
@@ -0,0 +1,148 @@
+//! Exports the specification trees of `demonstrate!` instances as Markdown documentation
+
+use crate::block::{Block, Describe, Root};
+use crate::export::{file_name, write_files};
+use proc_macro2::TokenStream;
+use std::fmt::Write;
+use syn::{Attribute, Lit, Meta, MetaNameValue};
+
+/// The environment variable naming the directory that documentation is exported to, which is
+/// relative to the manifest directory of the crate being compiled
+const DOCS_DIR_VAR: &str = "DEMONSTRATE_DOCS_DIR";
+
+/// Writes a Markdown file for each root `Describe` block to the directory named by
+/// `DEMONSTRATE_DOCS_DIR`, if it is set
+pub(crate) fn export(root: &Root) -> TokenStream {
+    write_files(DOCS_DIR_VAR, "documentation", || {
+        root.0
+            .iter()
+            .map(|describe| {
+                let name = file_name(&describe.properties.block_props.name, "md");
+                (name, markdown(describe))
+            })
+            .collect()
+    })
+}
+
+/// The Markdown documentation of a root `Describe` block
+fn markdown(describe: &Describe) -> String {
+    let mut markdown = String::new();
+    write_describe(&mut markdown, describe, 1);
+    markdown
+}
+
+/// Writes a heading for a `Describe` block followed by a list of its tests and benchmarks, then
+/// its nested `Describe` blocks with deeper headings
+fn write_describe(markdown: &mut String, describe: &Describe, depth: usize) {
+    let block_props = &describe.properties.block_props;
+    let _ = writeln!(
+        markdown,
+        "{} {}\n",
+        "#".repeat(depth.min(6)),
+        block_props.name
+    );
+    if let Some(doc) = doc(&describe.attributes) {
+        let _ = writeln!(markdown, "{}\n", doc);
+    }
+
+    let mut has_items = false;
+    for block in &describe.blocks {
+        let (block_props, suffix) = match block {
+            Block::Test(test) => (&test.properties, ""),
+            Block::CompileFail(compile_fail) => (&compile_fail.properties, " *(compile fail)*"),
+            Block::Bench(bench) => (&bench.properties, " *(benchmark)*"),
+            Block::Describe(_) => continue,
+        };
+
+        let _ = writeln!(markdown, "- {}{}", block_props.name, suffix);
+        if let Some(doc) = doc(&block_props.attributes) {
+            for line in doc.lines() {
+                let _ = writeln!(markdown, "  {}", line);
+            }
+        }
+        has_items = true;
+    }
+    if has_items {
+        markdown.push('\n');
+    }
+
+    for block in &describe.blocks {
+        if let Block::Describe(describe) = block {
+            write_describe(markdown, describe, depth + 1);
+        }
+    }
+}
+
+/// The lines of the doc comments within the given attributes
+fn doc(attributes: &[Attribute]) -> Option<String> {
+    let lines = attributes
+        .iter()
+        .filter(|attribute| attribute.path.is_ident("doc"))
+        .filter_map(|attribute| match attribute.parse_meta() {
+            Ok(Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(doc), ..
+            })) => Some(doc.value()),
+            _ => None,
+        })
+        .map(|line| line.strip_prefix(' ').map(str::to_owned).unwrap_or(line))
+        .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_the_specification_tree() {
+        let root = syn::parse_str::<Root>(
+            r#"
+            /// Parses numbers
+            describe "parser" {
+                before {
+                    let input = "4";
+                }
+
+                /// Accepts digits
+                /// of any length
+                it "parses digits" {}
+
+                context "with signs" {
+                    it "parses negative numbers" {}
+
+                    bench "parses quickly" {}
+                }
+
+                it "rejects strings" compile_fail {
+                    let number: u32 = "4";
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            markdown(&root.0[0]),
+            "# parser
+
+Parses numbers
+
+- parses digits
+  Accepts digits
+  of any length
+- rejects strings *(compile fail)*
+
+## with signs
+
+- parses negative numbers
+- parses quickly *(benchmark)*
+
+"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Writes files describing `demonstrate!` instances to directories named by environment variables
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::path::PathBuf;
+use std::{env, fs};
+use voca_rs::case::snake_case;
+
+/// Writes the given files to the directory named by the environment variable `var`, relative to
+/// the manifest directory of the crate being compiled, if it is set
+///
+/// The generated code reads the variable with `option_env!` so that Cargo expands the macro
+/// again when it changes, along with a compile error if writing the files fails.
+pub(crate) fn write_files(
+    var: &str,
+    description: &str,
+    files: impl FnOnce() -> Vec<(String, String)>,
+) -> TokenStream {
+    let error = try_write_files(var, description, files)
+        .err()
+        .map(|error| error.to_compile_error());
+
+    quote! {
+        const _: Option<&str> = option_env!(#var);
+        #error
+    }
+}
+
+/// The name of the file that the root `Describe` block with the given name is exported to
+///
+/// The name starts with those of the crate being compiled and the source file declaring the
+/// block, so that blocks with the same name in other test targets or files don't overwrite it.
+pub(crate) fn file_name(name: &str, extension: &str) -> String {
+    qualified_file_name(
+        env::var("CARGO_CRATE_NAME").ok().as_deref(),
+        &Span::call_site().file(),
+        name,
+        extension,
+    )
+}
+
+fn qualified_file_name(
+    crate_name: Option<&str>,
+    source_file: &str,
+    name: &str,
+    extension: &str,
+) -> String {
+    // The source file is unknown outside of a macro expansion, such as in unit tests
+    let source_file = Some(source_file)
+        .filter(|source_file| !source_file.starts_with('<'))
+        .map(|source_file| snake_case(source_file.trim_end_matches(".rs")));
+
+    crate_name
+        .map(str::to_owned)
+        .into_iter()
+        .chain(source_file)
+        .chain(Some(snake_case(name)))
+        .chain(Some(extension.to_owned()))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn try_write_files(
+    var: &str,
+    description: &str,
+    files: impl FnOnce() -> Vec<(String, String)>,
+) -> syn::Result<()> {
+    let dir = match env::var_os(var) {
+        Some(dir) => env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(dir),
+        None => return Ok(()),
+    };
+
+    let error = |error: std::io::Error| {
+        syn::Error::new(
+            Span::call_site(),
+            format!(
+                "Failed exporting {} to `{}`: {}",
+                description,
+                dir.display(),
+                error
+            ),
+        )
+    };
+
+    fs::create_dir_all(&dir).map_err(error)?;
+    for (file_name, contents) in files() {
+        fs::write(dir.join(file_name), contents).map_err(error)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_files_after_the_crate_and_source_file() {
+        let name =
+            |crate_name, source_file| qualified_file_name(crate_name, source_file, "Parser", "md");
+
+        assert_eq!(name(Some("a"), "tests/a.rs"), "a.tests_a.parser.md");
+        assert_eq!(name(Some("b"), "tests/b.rs"), "b.tests_b.parser.md");
+        assert_eq!(
+            name(Some("demonstrate"), "src/parser/mod.rs"),
+            "demonstrate.src_parser_mod.parser.md"
+        );
+        assert_eq!(name(None, "<unspecified>"), "parser.md");
+    }
+}
@@ -0,0 +1,344 @@
+//! Resolves the dependencies of `fixture` blocks and generates the code that sets up the fixtures
+//! requested by tests
+
+use crate::block::{Fixture, FixtureRequest, Scope};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Ident, Stmt, Type};
+
+/// A fixture declared by a `Describe` block, with its dependencies resolved
+#[derive(Clone)]
+pub(crate) struct FixtureDef {
+    /// The name that the fixture is requested by
+    pub(crate) name: Ident,
+    /// The name of the generated fixture, which is unique among the fixtures visible to a block
+    /// since fixtures declared by nested blocks can have the same name
+    pub(crate) id: Ident,
+    pub(crate) scope: Scope,
+    pub(crate) ty: Type,
+    /// The bindings of the fixture's dependencies, along with their ids
+    pub(crate) dependencies: Vec<(Ident, Ident)>,
+    pub(crate) setup: Vec<Stmt>,
+    pub(crate) teardown: Vec<Stmt>,
+}
+
+/// Resolves the fixtures declared by a block at the given depth against those declared by its
+/// ancestors, which the block's fixtures can depend on along with each other
+pub(crate) fn resolve(
+    declared: &[Fixture],
+    visible: &[FixtureDef],
+    depth: usize,
+) -> syn::Result<Vec<FixtureDef>> {
+    for (index, fixture) in declared.iter().enumerate() {
+        if declared[..index]
+            .iter()
+            .any(|other| other.name == fixture.name)
+        {
+            return Err(syn::Error::new(
+                fixture.name.span(),
+                format!("The fixture `{}` is already declared", fixture.name),
+            ));
+        }
+    }
+
+    let id = |name: &Ident| format_ident!("__demonstrate_fixture_{}_{}", depth, name);
+    let defs = declared
+        .iter()
+        .map(|fixture| {
+            let dependencies = fixture
+                .dependencies
+                .iter()
+                .map(|request| {
+                    // A block's own fixtures shadow those of its ancestors
+                    let (id, scope) =
+                        match declared.iter().find(|other| other.name == request.fixture) {
+                            Some(other) => (id(&other.name), other.scope),
+                            None => {
+                                let def = lookup(visible, &request.fixture)?;
+                                (def.id.clone(), def.scope)
+                            }
+                        };
+                    if fixture.scope == Scope::Describe && scope == Scope::Test {
+                        return Err(syn::Error::new(
+                            request.fixture.span(),
+                            format!(
+                                "The describe-scoped fixture `{}` cannot depend on the test-scoped \
+                                 fixture `{}`",
+                                fixture.name, request.fixture
+                            ),
+                        ));
+                    }
+                    Ok((request.binding.clone(), id))
+                })
+                .collect::<syn::Result<_>>()?;
+
+            Ok(FixtureDef {
+                name: fixture.name.clone(),
+                id: id(&fixture.name),
+                scope: fixture.scope,
+                ty: fixture.ty.clone(),
+                dependencies,
+                setup: fixture.setup.0.clone(),
+                teardown: fixture
+                    .teardown
+                    .as_ref()
+                    .map_or_else(Vec::new, |teardown| teardown.0.clone()),
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    // Only the block's own fixtures can depend on each other, since those of its ancestors were
+    // resolved before them
+    for def in &defs {
+        reject_cycles(def, &defs, &mut Vec::new())?;
+    }
+
+    Ok(defs)
+}
+
+/// Rejects a cycle among the dependencies of a fixture, given the fixtures that depend on it
+fn reject_cycles<'a>(
+    def: &'a FixtureDef,
+    defs: &'a [FixtureDef],
+    dependents: &mut Vec<&'a FixtureDef>,
+) -> syn::Result<()> {
+    if let Some(start) = dependents
+        .iter()
+        .position(|dependent| dependent.id == def.id)
+    {
+        let cycle = dependents[start..]
+            .iter()
+            .chain(Some(&def))
+            .map(|def| format!("`{}`", def.name))
+            .collect::<Vec<_>>();
+        return Err(syn::Error::new(
+            def.name.span(),
+            format!(
+                "The fixture `{}` depends on itself: {}",
+                def.name,
+                cycle.join(" -> ")
+            ),
+        ));
+    }
+
+    dependents.push(def);
+    for (_, id) in &def.dependencies {
+        if let Some(dependency) = defs.iter().find(|other| &other.id == id) {
+            reject_cycles(dependency, defs, dependents)?;
+        }
+    }
+    dependents.pop();
+
+    Ok(())
+}
+
+/// Finds the innermost fixture with the given name
+fn lookup<'a>(visible: &'a [FixtureDef], name: &Ident) -> syn::Result<&'a FixtureDef> {
+    visible
+        .iter()
+        .rev()
+        .find(|def| &def.name == name)
+        .ok_or_else(|| syn::Error::new(name.span(), format!("Unknown fixture `{}`", name)))
+}
+
+/// Merges the fixtures requested by a block with those requested by its parent, which it can
+/// override by binding another fixture to the same name
+pub(crate) fn merge_requests(
+    parent: &[FixtureRequest],
+    requests: &[FixtureRequest],
+) -> Vec<FixtureRequest> {
+    parent
+        .iter()
+        .filter(|request| {
+            requests
+                .iter()
+                .all(|other| other.binding != request.binding)
+        })
+        .chain(requests.iter())
+        .cloned()
+        .collect()
+}
+
+/// Orders the requested fixtures and their dependencies so that each comes after its
+/// dependencies
+fn set_up_order<'a>(
+    requests: &[FixtureRequest],
+    visible: &'a [FixtureDef],
+) -> syn::Result<Vec<&'a FixtureDef>> {
+    fn visit<'a>(def: &'a FixtureDef, visible: &'a [FixtureDef], order: &mut Vec<&'a FixtureDef>) {
+        if order.iter().any(|other| other.id == def.id) {
+            return;
+        }
+        for (_, id) in &def.dependencies {
+            if let Some(dependency) = visible.iter().find(|other| &other.id == id) {
+                visit(dependency, visible, order);
+            }
+        }
+        order.push(def);
+    }
+
+    let mut order = Vec::new();
+    for request in requests {
+        visit(lookup(visible, &request.fixture)?, visible, &mut order);
+    }
+
+    Ok(order)
+}
+
+/// Generates the statements that lease the describe-scoped fixtures among the requested ones and
+/// their dependencies, which must precede those of `set_up_stmts` and anything that can skip the
+/// test, so that the fixtures are torn down once every test using them has finished
+pub(crate) fn lease_stmts(requests: &[FixtureRequest], visible: &[FixtureDef]) -> Vec<Stmt> {
+    set_up_order(requests, visible)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|def| def.scope == Scope::Describe)
+        .map(|FixtureDef { id, .. }| parse_quote!(let #id = #id();))
+        .collect()
+}
+
+/// The generated functions returning the state of the describe-scoped fixtures among the
+/// requested ones and their dependencies, which a test leasing them is registered with
+pub(crate) fn shared_fns(requests: &[FixtureRequest], visible: &[FixtureDef]) -> Vec<Ident> {
+    set_up_order(requests, visible)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|def| def.scope == Scope::Describe)
+        .map(|FixtureDef { id, .. }| format_ident!("{}_shared", id))
+        .collect()
+}
+
+/// Generates the statements that set up the requested fixtures after their dependencies, which
+/// are torn down in the reverse order when the test finishes, followed by the bindings of the
+/// requested fixtures
+///
+/// The describe-scoped fixtures must have been leased by the statements of `lease_stmts`.
+pub(crate) fn set_up_stmts(
+    requests: &[FixtureRequest],
+    visible: &[FixtureDef],
+) -> syn::Result<Vec<Stmt>> {
+    let order = set_up_order(requests, visible)?;
+
+    // Fixtures are shared with those that depend on them, while tests can mutate test-scoped ones
+    let access = |id: &Ident, mutable: bool| -> TokenStream {
+        let def = order.iter().find(|def| &def.id == id).unwrap();
+        match (def.scope, mutable) {
+            (Scope::Test, false) => quote!(&*#id),
+            (Scope::Test, true) => quote!(&mut *#id),
+            (Scope::Describe, _) => quote!(&#id.value),
+        }
+    };
+
+    let mut stmts = order
+        .iter()
+        .map(|def| -> Stmt {
+            let FixtureDef {
+                name,
+                id,
+                scope,
+                ty,
+                dependencies,
+                setup,
+                teardown,
+            } = def;
+            match scope {
+                Scope::Test => {
+                    let bindings = dependencies.iter().map(|(binding, id)| {
+                        let access = access(id, false);
+                        quote! {
+                            #[allow(unused_variables)]
+                            let #binding = #access;
+                        }
+                    });
+                    parse_quote! {
+                        #[allow(unused_mut)]
+                        let mut #id = ::demonstrate::__private::Fixture::new(
+                            {
+                                #(#bindings)*
+                                #(#setup)*
+                            },
+                            |#name: &mut #ty| {
+                                #[allow(unused_variables)]
+                                let #name = #name;
+                                #(#teardown)*
+                            },
+                        );
+                    }
+                }
+                Scope::Describe => parse_quote!(#id.set_up();),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    stmts.extend(requests.iter().map(|request| -> Stmt {
+        let binding = &request.binding;
+        let access = access(&lookup(visible, &request.fixture).unwrap().id, true);
+        parse_quote! {
+            #[allow(unused_variables)]
+            let #binding = #access;
+        }
+    }));
+
+    Ok(stmts)
+}
+
+/// Generates the shared state of a describe-scoped fixture, which is set up for the first test to
+/// lease it and torn down once every test registered as leasing it has finished with it
+pub(crate) fn generate_shared(def: &FixtureDef) -> TokenStream {
+    let FixtureDef {
+        name,
+        id,
+        ty,
+        dependencies,
+        setup,
+        teardown,
+        ..
+    } = def;
+    let state = format_ident!("{}_state", id);
+    let shared = format_ident!("{}_shared", id);
+
+    // The leases of the dependencies are held by the test, so that each is torn down after the
+    // last of the fixtures depending on it
+    let dependency_ids = dependencies.iter().map(|(_, id)| id);
+    let dependency_shared = dependencies
+        .iter()
+        .map(|(_, id)| format_ident!("{}_shared", id));
+    let bindings = dependencies.iter().map(|(binding, _)| binding);
+    let binding_ids = dependencies.iter().map(|(_, id)| id);
+
+    quote! {
+        #[allow(non_camel_case_types)]
+        struct #state {
+            value: #ty,
+        }
+
+        #[allow(dead_code)]
+        fn #shared() -> &'static ::demonstrate::__private::Shared<#state> {
+            static STATE: ::demonstrate::__private::Shared<#state> =
+                ::demonstrate::__private::Shared::new();
+            &STATE
+        }
+
+        #[allow(dead_code)]
+        fn #id() -> ::demonstrate::__private::Lease<#state> {
+            #shared().lease(
+                #shared().users(),
+                || {
+                    #(let #dependency_ids = #dependency_shared().current();)*
+                    #(
+                        #[allow(unused_variables)]
+                        let #bindings = &#binding_ids.value;
+                    )*
+                    #state {
+                        value: { #(#setup)* },
+                    }
+                },
+                |state| {
+                    #[allow(unused_variables)]
+                    let #name = &state.value;
+                    #(#teardown)*
+                },
+            )
+        }
+    }
+}
@@ -0,0 +1,1030 @@
+//! Defines the code translations for the various macro components
+
+use crate::block::*;
+use crate::fixture;
+use crate::hooks;
+use crate::inherit::Inherit;
+use crate::unused::remove_unused;
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{
+    parse_quote, Attribute, Lit, Local, Meta, MetaNameValue, NestedMeta, Pat, PatIdent, PatType,
+    Stmt, Type,
+};
+use voca_rs::case::snake_case;
+
+/// The trait and respective function for generating the corresponding code translations
+pub(crate) trait Generate {
+    fn generate(&mut self, parent_props: Option<&DescribeProps>) -> TokenStream;
+}
+
+/// Generates the root `Describe` blocks within the macro, adding the `#[cfg(test)]` outer
+/// attribute to each, followed by the doctests of their `compile_fail` tests
+impl Generate for Root {
+    fn generate(&mut self, _parent_props: Option<&DescribeProps>) -> TokenStream {
+        self.0
+            .iter_mut()
+            .map(|block| {
+                let root_block = block.generate(None);
+                let doctests = block.doctests();
+                let doctests = (!doctests.is_empty()).then(|| {
+                    quote! {
+                        #[cfg(doctest)]
+                        #doctests
+                    }
+                });
+                quote! {
+                    #[cfg(test)]
+                    #root_block
+
+                    #doctests
+                }
+            })
+            .collect::<TokenStream>()
+    }
+}
+
+/// Determines the respective generate function to call for each block type
+impl Generate for Block {
+    fn generate(&mut self, parent_props: Option<&DescribeProps>) -> TokenStream {
+        match self {
+            Block::Test(test) => test.generate(parent_props),
+            // Rustdoc doesn't see the `#[cfg(test)]` modules, so `compile_fail` tests are
+            // generated by `Describe::doctests` instead
+            Block::CompileFail(compile_fail) => {
+                if let Some(parent_props) = parent_props {
+                    compile_fail.inherit(parent_props);
+                }
+                compile_fail.reject_target()
+            }
+            Block::Describe(describe) => describe.generate(parent_props),
+            Block::Bench(bench) => bench.generate(parent_props),
+        }
+    }
+}
+
+/// Generates a `mod` block with inherited properties
+impl Generate for Describe {
+    fn generate(&mut self, parent_props: Option<&DescribeProps>) -> TokenStream {
+        // Generate corresponding `use` statements
+        let mut uses = self
+            .properties
+            .uses
+            .iter()
+            .map(|use_tree| quote!(use #use_tree;))
+            .collect::<TokenStream>();
+
+        // Time this block's own hooks before they're merged with those of its ancestors
+        let depth = parent_props.map_or(0, |parent_props| parent_props.block_props.path.len());
+        self.time_hooks(depth);
+
+        // Fields of the context can't be declared by several blocks, since each test has one
+        let declares_state = !self.properties.state.is_empty();
+        let ancestors_state = parent_props.map_or(&[][..], |parent_props| &parent_props.state[..]);
+        for (index, field) in self.properties.state.iter().enumerate() {
+            if ancestors_state
+                .iter()
+                .chain(&self.properties.state[..index])
+                .any(|other| other.name == field.name)
+            {
+                return syn::Error::new(
+                    field.name.span(),
+                    format!("The context already has a `{}` field", field.name),
+                )
+                .to_compile_error();
+            }
+        }
+
+        // Inherit parent's `DescribeProps`
+        if let Some(parent_props) = parent_props {
+            self.inherit(parent_props);
+            uses.extend(quote!(
+                use super::*;
+            ));
+        }
+
+        // Generate the state shared by the `before_all` and `after_all` blocks
+        let shared = if self.before_all.is_some() || self.after_all.is_some() {
+            Some(self.generate_shared())
+        } else {
+            None
+        };
+
+        // Resolve the fixtures declared by this block, generating the state of those that are
+        // shared by its tests
+        let fixtures = match fixture::resolve(&self.fixtures, &self.properties.fixtures, depth) {
+            Ok(fixtures) => fixtures,
+            Err(error) => return error.to_compile_error(),
+        };
+        self.properties.fixtures.extend(fixtures.iter().cloned());
+        let shared_fixtures = fixtures
+            .iter()
+            .filter(|def| def.scope == Scope::Describe)
+            .map(fixture::generate_shared)
+            .collect::<TokenStream>();
+
+        // Generate the context holding the fields declared by this block and its ancestors
+        let context = if declares_state {
+            let fields = self.properties.state.iter().map(|field| &field.name);
+            let types = self.properties.state.iter().map(|field| &field.ty);
+            Some(quote! {
+                #[allow(dead_code)]
+                struct __DemonstrateContext {
+                    #(#fields: #types,)*
+                }
+            })
+        } else {
+            None
+        };
+
+        // Generate the resources declared by this block
+        let resources = self
+            .resources
+            .iter_mut()
+            .map(|resource| resource.generate(None))
+            .collect::<TokenStream>();
+
+        // Generate corresponding subblocks, with the tests sharing the `before` and `after` code
+        // sequences where they can
+        let cloned_props = self.properties.clone();
+        let mut blocks = TokenStream::new();
+        let mut tests = Vec::new();
+        for block in &mut self.blocks {
+            match block {
+                Block::Test(test) => tests.push(&mut **test),
+                block => blocks.extend(block.generate(Some(&cloned_props))),
+            }
+        }
+        blocks.extend(hooks::generate_tests(tests, &cloned_props));
+
+        // Assign module ident based on name
+        let ident = Ident::new(
+            &snake_case(&self.properties.block_props.name),
+            Span::call_site(),
+        );
+
+        let attributes = &self.attributes;
+        quote! {
+            #(#attributes)*
+            mod #ident {
+                #uses
+
+                #shared
+
+                #context
+
+                #resources
+
+                #shared_fixtures
+
+                #blocks
+            }
+        }
+    }
+}
+
+impl Describe {
+    /// Surrounds the `before` and `after` code sequences with statements that time them for the
+    /// runner of `demonstrate::main!()`
+    fn time_hooks(&mut self, depth: usize) {
+        let hooks = [
+            (&mut self.properties.before, quote!(Before)),
+            (&mut self.properties.after, quote!(After)),
+        ];
+        for (block, hook) in hooks {
+            if let Some(BasicBlock(stmts)) = block {
+                let start: Stmt = parse_quote! {
+                    let __demonstrate_hook_timer: ::demonstrate::__private::HookTimer =
+                        ::demonstrate::__private::HookTimer::start();
+                };
+                let finish: Stmt = parse_quote! {
+                    __demonstrate_hook_timer.finish(
+                        &__demonstrate_hook_timings,
+                        #depth,
+                        ::demonstrate::__private::Hook::#hook,
+                    );
+                };
+                stmts.insert(0, start);
+                // A trailing expression is the value of the test, so it's evaluated before the
+                // hook is timed and then returned again
+                match stmts.pop() {
+                    Some(Stmt::Expr(tail)) => stmts.extend([
+                        parse_quote!(let __demonstrate_hook_value = #tail;),
+                        finish,
+                        Stmt::Expr(parse_quote!(__demonstrate_hook_value)),
+                    ]),
+                    last => stmts.extend(last.into_iter().chain(Some(finish))),
+                }
+            }
+        }
+    }
+
+    /// Generates the doctests of the `compile_fail` tests within this block, in modules mirroring
+    /// the generated ones, once it has been generated
+    fn doctests(&mut self) -> TokenStream {
+        let doctests = self
+            .blocks
+            .iter_mut()
+            .map(|block| match block {
+                Block::Describe(describe) => describe.doctests(),
+                Block::CompileFail(compile_fail) => compile_fail.generate(None),
+                _ => TokenStream::new(),
+            })
+            .collect::<TokenStream>();
+        if doctests.is_empty() {
+            return doctests;
+        }
+
+        let ident = Ident::new(
+            &snake_case(&self.properties.block_props.name),
+            Span::call_site(),
+        );
+        let attributes = &self.attributes;
+        quote! {
+            #(#attributes)*
+            mod #ident {
+                #doctests
+            }
+        }
+    }
+
+    /// Generates the state set up by the `before_all` block and torn down by the `after_all`
+    /// block once every descendant test has finished with it, registering it to be leased by
+    /// those tests
+    fn generate_shared(&mut self) -> TokenStream {
+        // Ancestors' states are inherited through `use super::*`, so each needs a unique name
+        let index = self.properties.shared.len();
+        let state = format_ident!("__DemonstrateSharedState{}", index);
+        let lease = format_ident!("__demonstrate_before_all_{}", index);
+
+        let before_all = self
+            .before_all
+            .take()
+            .map_or_else(Vec::new, |block| block.0);
+        let after_all = self.after_all.take().map_or_else(Vec::new, |block| block.0);
+
+        // Only `let` bindings with a type annotation are shared, as they are stored in a static
+        let (fields, types): (Vec<Ident>, Vec<Type>) = before_all
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Local(Local {
+                    pat: Pat::Type(PatType { pat, ty, .. }),
+                    ..
+                }) => match &**pat {
+                    Pat::Ident(PatIdent { ident, .. }) => Some((ident.clone(), (**ty).clone())),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unzip();
+
+        // The hooks are executed on their own threads, so async hooks use their own executor
+        // which, for Tokio, is kept alive alongside the state for the resources it drives
+        let BlockProps {
+            is_async, runtime, ..
+        } = &self.properties.block_props;
+        let (runtime_field, runtime_init, runtime_field_init, runtime_ref) =
+            match (is_async, runtime) {
+                (true, Some(Runtime::Tokio { .. })) => (
+                    quote!(__runtime: ::tokio::runtime::Runtime,),
+                    quote! {
+                        let __runtime = ::tokio::runtime::Builder::new_multi_thread()
+                            .worker_threads(1)
+                            .enable_all()
+                            .build()
+                            .expect("Failed building the Tokio runtime");
+                    },
+                    quote!(__runtime,),
+                    quote!(let __runtime = &state.__runtime;),
+                ),
+                _ => (quote!(), quote!(), quote!(), quote!()),
+            };
+        let block_on = |block: TokenStream| match (is_async, runtime) {
+            (false, _) => block,
+            (true, Some(Runtime::Tokio { .. })) => quote!(__runtime.block_on(async #block)),
+            (true, Some(runtime)) => runtime.block_on(quote!(async #block)),
+            (true, None) => Runtime::BlockOn.block_on(quote!(async #block)),
+        };
+        // A `before_all` block without shared fields returns nothing rather than an empty tuple
+        let fields_tuple = (!fields.is_empty()).then(|| quote!((#(#fields,)*)));
+        let setup = block_on(quote!({
+            #(#before_all)*
+            #fields_tuple
+        }));
+        let teardown = block_on(quote!({ #(#after_all)* }));
+
+        let shared_state = SharedState {
+            lease: lease.clone(),
+            fields: fields.clone(),
+        };
+        let shared = shared_state.shared_fn();
+        self.properties.shared.push(shared_state);
+
+        quote! {
+            struct #state {
+                #runtime_field
+                #(#fields: #types,)*
+            }
+
+            #[allow(dead_code)]
+            fn #shared() -> &'static ::demonstrate::__private::Shared<#state> {
+                static STATE: ::demonstrate::__private::Shared<#state> =
+                    ::demonstrate::__private::Shared::new();
+                &STATE
+            }
+
+            #[allow(dead_code)]
+            fn #lease() -> ::demonstrate::__private::Lease<#state> {
+                #shared().lease(
+                    #shared().users(),
+                    || {
+                        #runtime_init
+                        let (#(#fields,)*) = #setup;
+                        #state {
+                            #runtime_field_init
+                            #(#fields,)*
+                        }
+                    },
+                    |state| {
+                        #runtime_ref
+                        #(
+                            #[allow(unused_variables)]
+                            let #fields = &state.#fields;
+                        )*
+                        #teardown
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Generates a function returning the resource, which is set up for the first test to lease it
+impl Generate for Resource {
+    fn generate(&mut self, _parent_props: Option<&DescribeProps>) -> TokenStream {
+        let Resource {
+            name,
+            pool,
+            ty,
+            setup,
+            teardown,
+        } = self;
+        let ident = Ident::new(&snake_case(&name.value()), name.span());
+        let setup = &setup.0;
+        let teardown = teardown
+            .as_ref()
+            .map_or(&[][..], |teardown| &teardown.0[..]);
+
+        // A shared instance is reference counted, while each instance of a pool is leased
+        // exclusively, so the teardown can mutate the latter
+        let (resource, new) = match pool {
+            None => (
+                quote!(Resource),
+                quote! {
+                    Resource::new(#name, || ::std::sync::Arc::new({ #(#setup)* }), |#ident| {
+                        #[allow(unused_variables)]
+                        let #ident = &*#ident;
+                        #(#teardown)*
+                    })
+                },
+            ),
+            Some(size) => (
+                quote!(Pool),
+                quote! {
+                    Pool::new(#name, #size, || { #(#setup)* }, |mut #ident| {
+                        #[allow(unused_variables)]
+                        let #ident = &mut #ident;
+                        #(#teardown)*
+                    })
+                },
+            ),
+        };
+
+        quote! {
+            #[allow(dead_code)]
+            pub(crate) fn #ident() -> &'static ::demonstrate::__private::#resource<#ty> {
+                static RESOURCE: ::demonstrate::__private::#resource<#ty> =
+                    ::demonstrate::__private::#new;
+                &RESOURCE
+            }
+        }
+    }
+}
+
+/// Generates the statements that lease the given resources and bind their instances
+fn lease_resources(resources: &[syn::Path]) -> Vec<Stmt> {
+    resources
+        .iter()
+        .map(|resource| {
+            let ident = &resource.segments.last().unwrap().ident;
+            parse_quote! {
+                #[allow(unused_variables, unused_mut)]
+                let mut #ident = #resource().lease();
+            }
+        })
+        .collect()
+}
+
+/// Generates the expectation of a death test that `death_test` checks its process against
+impl ToTokens for Death {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let exit = match &self.exit {
+            Exit::Code(code) => {
+                let code = code.base10_parse::<i32>().unwrap();
+                quote!(Code(#code))
+            }
+            Exit::Abort => quote!(Abort),
+            Exit::Any => quote!(Any),
+        };
+        let stderr = &self.stderr;
+        tokens.extend(quote! {
+            ::demonstrate::__private::Death {
+                exit: ::demonstrate::__private::Exit::#exit,
+                stderr: &[#(#stderr),*],
+            }
+        });
+    }
+}
+
+/// Whether a block was marked with `#[ignore]`
+pub(crate) fn is_ignored(props: &BlockProps) -> bool {
+    props
+        .attributes
+        .iter()
+        .any(|attribute| attribute.path.is_ident("ignore"))
+}
+
+/// Generates a unit test with inherited properties
+impl Generate for Test {
+    fn generate(&mut self, parent_props: Option<&DescribeProps>) -> TokenStream {
+        let death_preamble = match self.prepare(parent_props) {
+            Ok(death_preamble) => death_preamble,
+            Err(error) => return error.to_compile_error(),
+        };
+        self.inline_hooks();
+
+        self.generate_fns(death_preamble)
+    }
+}
+
+impl Test {
+    /// Inherits the parent's `BlockProps` and `before`/`after` code sequences, then prepends the
+    /// test's leases and preamble to its setup
+    ///
+    /// The preamble of a death test is returned instead, to be run before its child process is
+    /// spawned.
+    pub(crate) fn prepare(
+        &mut self,
+        parent_props: Option<&DescribeProps>,
+    ) -> syn::Result<Vec<Stmt>> {
+        if let Some(parent_props) = parent_props {
+            self.inherit(parent_props);
+        }
+
+        self.reject_contradictions()?;
+
+        // Skip the test before anything else if the environment variables it requires aren't set
+        let required_env = self.properties.required_env.iter().map(|variable| -> Stmt {
+            parse_quote! {
+                ::demonstrate::assume!(
+                    ::std::env::var_os(#variable).is_some(),
+                    "the {} environment variable is not set",
+                    #variable
+                );
+            }
+        });
+        let mut preamble = required_env.collect::<Vec<_>>();
+
+        // Hold the serial locks from before the `before` code sequences until after the `after`
+        // code sequences
+        let serial = &self.properties.serial;
+        if !serial.is_empty() {
+            preamble.push(parse_quote! {
+                let __demonstrate_serial = ::demonstrate::__private::serial(&[#(#serial),*]);
+            });
+        }
+
+        // A death test's code sequences run in a child process, which is only spawned once the
+        // test isn't skipped and holds its locks
+        let death_preamble = match &self.expectation {
+            Some(Expectation::Death(_)) => std::mem::take(&mut preamble),
+            _ => Vec::new(),
+        };
+
+        // Lease the resources for the `before` code sequences to use as well, after the state
+        // that's leased before the test can be skipped
+        preamble.extend(lease_resources(&self.properties.resources));
+        let leases = std::mem::take(&mut self.leases);
+        self.setup.splice(0..0, leases.into_iter().chain(preamble));
+
+        Ok(death_preamble)
+    }
+
+    /// Surrounds the test's own code sequence with its setup, the parts of the `before` code
+    /// sequences that it uses, and the `after` code sequences
+    pub(crate) fn inline_hooks(&mut self) {
+        let content = std::mem::take(&mut self.content.0);
+        let after = std::mem::take(&mut self.after);
+        let rest = content.iter().chain(&after).cloned().collect::<Vec<_>>();
+        let before = remove_unused(&self.before, &rest);
+
+        let returns = hooks::return_type(self).is_some();
+        self.content.0 = std::mem::take(&mut self.setup)
+            .into_iter()
+            .chain(before)
+            .chain(hooks::then_after(content, after, returns))
+            .collect();
+    }
+
+    /// Generates the `#[test]` function running the test's content, along with the function that
+    /// `demonstrate::main!()` runs instead and its registration
+    pub(crate) fn generate_fns(&mut self, death_preamble: Vec<Stmt>) -> TokenStream {
+        let BlockProps {
+            attributes,
+            is_async,
+            runtime,
+            name,
+            span,
+            return_type,
+            ..
+        } = &self.properties;
+        let content = &self.content.0;
+
+        // Assign test ident based on name
+        let ident = Ident::new(&snake_case(name), Span::call_site());
+        // The value returned by the code sequence of a test with an expectation is checked
+        // rather than returned
+        let return_tokens = return_type
+            .as_ref()
+            .filter(|_| self.expectation.is_none())
+            .map(|return_type| quote!(-> #return_type));
+
+        // Without a runtime, an async test must be run by a test attribute such as
+        // `#[tokio::test]`
+        let test_attribute = attributes
+            .iter()
+            .find(|attribute| is_test_attribute(attribute));
+        if *is_async && runtime.is_none() && test_attribute.is_none() {
+            return syn::Error::new(
+                *span,
+                "This async test has no way to be executed: select a runtime with \
+                 `async(tokio)`, `async(async_std)` or `async(block_on)`, or provide a test \
+                 attribute such as `#[tokio::test]`",
+            )
+            .to_compile_error();
+        }
+
+        let body = match runtime {
+            Some(runtime) if *is_async => runtime.block_on(quote!(async { #(#content)* })),
+            _ => quote!(#(#content)*),
+        };
+        let body = match &self.expectation {
+            Some(Expectation::Death(death)) => {
+                let name = ident.to_string();
+                let return_type = return_type
+                    .as_ref()
+                    .map_or_else(|| quote!(()), ToTokens::to_token_stream);
+                quote! {
+                    #(#death_preamble)*
+                    ::demonstrate::__private::death_test(
+                        module_path!(),
+                        #name,
+                        #death,
+                        || -> #return_type { #body },
+                    )
+                }
+            }
+            _ => body,
+        };
+
+        // The `#[test]` function is removed from targets without the libtest harness, so the
+        // test's body is generated in a separate function that `demonstrate::main!()` can call
+        let body_ident = format_ident!("__demonstrate_{}", ident);
+        let body_attributes = attributes
+            .iter()
+            .filter(|attribute| !is_libtest_attribute(attribute));
+
+        let registration = match self.registration(&body_ident) {
+            Ok(registration) => registration,
+            Err(error) => return error.to_compile_error(),
+        };
+        // The test attribute generates the `#[test]` function, so the runner executes the
+        // test's code sequences on the same runtime, if it's known
+        if let (true, None, Some(test_attribute)) = (*is_async, runtime, test_attribute) {
+            let async_ident = format_ident!("__demonstrate_async_{}", ident);
+            let body = match Runtime::from_test_attribute(test_attribute) {
+                Some(runtime) => runtime.block_on(quote!(#async_ident())),
+                None => {
+                    let message =
+                        format!(
+                        "This async test is run by `#[{}]`, which only the libtest harness can \
+                         call: select a runtime with `async(tokio)`, `async(async_std)` or \
+                         `async(block_on)` for `demonstrate::main!()` to run it",
+                        test_attribute.path.to_token_stream().to_string().replace(' ', "")
+                    );
+                    quote!(::std::panic!(#message))
+                }
+            };
+            let async_attributes = body_attributes.clone();
+
+            return quote! {
+                #(#attributes)*
+                async fn #ident() #return_tokens {
+                    #async_ident().await
+                }
+
+                #[allow(dead_code)]
+                #(#async_attributes)*
+                async fn #async_ident() #return_tokens {
+                    #(#content)*
+                }
+
+                #(#body_attributes)*
+                fn #body_ident() #return_tokens {
+                    #body
+                }
+
+                #registration
+            };
+        }
+
+        let libtest = match &self.properties.xfail {
+            Some(reason) => quote!(::demonstrate::__private::libtest_xfail(#body_ident, #reason)),
+            None => quote!(::demonstrate::__private::libtest(#body_ident)),
+        };
+
+        quote! {
+            #[test]
+            #(#attributes)*
+            fn #ident() #return_tokens {
+                #libtest
+            }
+
+            #(#body_attributes)*
+            fn #body_ident() #return_tokens {
+                #body
+            }
+
+            #registration
+        }
+    }
+
+    /// Whether this test expects to end its process, so that it's run in a child process
+    pub(crate) fn is_death_test(&self) -> bool {
+        matches!(self.expectation, Some(Expectation::Death(_)))
+    }
+
+    /// Rejects attributes that contradict each other or the test's other properties, once it has
+    /// inherited those of its ancestors
+    fn reject_contradictions(&self) -> syn::Result<()> {
+        let BlockProps {
+            attributes,
+            is_async,
+            runtime,
+            span,
+            return_type,
+            ..
+        } = &self.properties;
+
+        // Libtest only checks that tests returning `()` panic
+        let returns_unit = |ty: &Type| matches!(ty, Type::Tuple(tuple) if tuple.elems.is_empty());
+        if self.expectation.is_none()
+            && return_type.as_ref().is_some_and(|ty| !returns_unit(ty))
+            && attributes
+                .iter()
+                .any(|attribute| attribute.path.is_ident("should_panic"))
+        {
+            return Err(syn::Error::new(
+                *span,
+                "`#[should_panic]` can only be applied to tests returning `()`, which this test \
+                 doesn't",
+            ));
+        }
+
+        let should_panic = attributes
+            .iter()
+            .any(|attribute| attribute.path.is_ident("should_panic"));
+        match &self.expectation {
+            Some(_) if should_panic => {
+                return Err(syn::Error::new(
+                    *span,
+                    "`#[should_panic]` can't be applied to a test that declares its expected \
+                     outcome, which can be left out with `#[no_inherit(should_panic)]` if it was \
+                     inherited",
+                ))
+            }
+            // The test attribute would run the test rather than `death_test`
+            Some(Expectation::Death(_)) if *is_async && runtime.is_none() => {
+                return Err(syn::Error::new(
+                    *span,
+                    "A death test can only be async when it selects a runtime, rather than being \
+                     run by a test attribute",
+                ))
+            }
+            Some(Expectation::Error(_)) if return_type.as_ref().map_or(true, returns_unit) => {
+                return Err(syn::Error::new(
+                    *span,
+                    "`errs_with` can only be declared by tests returning a `Result`",
+                ))
+            }
+            _ => {}
+        }
+
+        // An expected failure would be reported as a failure of the test if it also expected a
+        // panic, and can't be caught by the tests run by a test attribute
+        if self.properties.xfail.is_some() {
+            if should_panic {
+                return Err(syn::Error::new(
+                    *span,
+                    "`#[should_panic]` can't be applied to a test marked with `xfail`, which can \
+                     be left out with `#[no_inherit(should_panic)]` if it was inherited",
+                ));
+            }
+            if *is_async && runtime.is_none() {
+                return Err(syn::Error::new(
+                    *span,
+                    "`xfail` can only be applied to an async test that selects a runtime, rather \
+                     than being run by a test attribute",
+                ));
+            }
+        }
+
+        // A test attribute would run the test on its own runtime as well
+        if *is_async && runtime.is_some() && attributes.iter().any(is_test_attribute) {
+            return Err(syn::Error::new(
+                *span,
+                "This async test selects a runtime but also has a test attribute, which can be \
+                 left out with `#[no_inherit(..)]` if it was inherited",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Generates the registration of this test's body with `demonstrate::main!()`
+    fn registration(&self, body_ident: &Ident) -> syn::Result<TokenStream> {
+        let BlockProps {
+            attributes,
+            path,
+            span,
+            serial,
+            resources,
+            ..
+        } = &self.properties;
+
+        let start = span.start();
+        let (line, column) = (start.line as u32, start.column as u32 + 1);
+        let name = snake_case(&self.properties.name);
+        let ignored = is_ignored(&self.properties);
+        let xfail = match &self.properties.xfail {
+            Some(reason) => quote!(::std::option::Option::Some(#reason)),
+            None => quote!(::std::option::Option::None),
+        };
+        let should_panic = match attributes
+            .iter()
+            .find(|attribute| attribute.path.is_ident("should_panic"))
+        {
+            None => quote!(No),
+            Some(attribute) => match attribute.parse_meta()? {
+                Meta::Path(_) => quote!(Yes),
+                Meta::NameValue(MetaNameValue {
+                    lit: Lit::Str(expected),
+                    ..
+                }) => quote!(WithMessage(#expected)),
+                Meta::List(list) => match list.nested.first() {
+                    Some(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                        path,
+                        lit: Lit::Str(expected),
+                        ..
+                    }))) if path.is_ident("expected") => quote!(WithMessage(#expected)),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            list,
+                            "Expected `#[should_panic(expected = \"..\")]`",
+                        ))
+                    }
+                },
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "Expected `#[should_panic = \"..\"]`",
+                    ))
+                }
+            },
+        };
+
+        // Death tests leave leasing the shared state to their child processes
+        let shared = if self.is_death_test() {
+            &[][..]
+        } else {
+            &self.shared[..]
+        };
+        // The registration is left out of the build along with the test's function
+        let cfgs = attributes
+            .iter()
+            .filter(|attribute| attribute.path.is_ident("cfg"));
+
+        Ok(quote! {
+            #(#cfgs)*
+            ::demonstrate::__private::inventory::submit! {
+                ::demonstrate::__private::TestCase {
+                    path: &[#(#path),*],
+                    module_path: module_path!(),
+                    name: #name,
+                    file: file!(),
+                    line: #line,
+                    column: #column,
+                    ignored: #ignored,
+                    should_panic: ::demonstrate::__private::ShouldPanic::#should_panic,
+                    xfail: #xfail,
+                    serial: &[#(#serial),*],
+                    resources: &[#(
+                        || -> &'static dyn ::demonstrate::__private::Users { #resources() }
+                    ),*],
+                    shared: &[#(
+                        || -> &'static dyn ::demonstrate::__private::Leased { #shared() }
+                    ),*],
+                    run: || ::demonstrate::__private::Report::report(#body_ident()),
+                }
+            }
+        })
+    }
+}
+
+/// Whether an attribute only applies to tests run by the libtest harness
+pub(crate) fn is_libtest_attribute(attribute: &Attribute) -> bool {
+    attribute.path.is_ident("ignore")
+        || attribute.path.is_ident("should_panic")
+        || is_test_attribute(attribute)
+}
+
+/// Generates a function documented by a `compile_fail` doctest of the test's snippet
+///
+/// The snippet is formatted from its tokens, which rustdoc compiles on its own without the
+/// crate's `use` declarations, and expects to fail with the error code if one was given.
+impl Generate for CompileFail {
+    fn generate(&mut self, parent_props: Option<&DescribeProps>) -> TokenStream {
+        if let Some(parent_props) = parent_props {
+            self.inherit(parent_props);
+        }
+
+        let BlockProps { name, span, .. } = &self.properties;
+        let ident = Ident::new(&snake_case(name), Span::call_site());
+        let language = match &self.code {
+            Some(code) => format!("compile_fail,{}", code),
+            None => "compile_fail".to_owned(),
+        };
+        let doc = format!("```{}\n{}\n```", language, self.content);
+        quote_spanned! {*span=>
+            #[doc = #doc]
+            #[allow(dead_code)]
+            fn #ident() {}
+        }
+    }
+}
+
+impl CompileFail {
+    /// Rejects the test if it's declared in a target that rustdoc doesn't collect doctests from,
+    /// where it would never run
+    ///
+    /// Only the library's `#[cfg(test)]` modules can't be told apart from the rest of it.
+    fn reject_target(&self) -> TokenStream {
+        // Cargo sets these variables for binaries and examples, and for integration tests and
+        // benchmarks respectively
+        let target = ["CARGO_BIN_NAME", "CARGO_TARGET_TMPDIR"]
+            .iter()
+            .any(|variable| std::env::var_os(variable).is_some());
+        if !target {
+            return TokenStream::new();
+        }
+        syn::Error::new(
+            self.properties.span,
+            "`compile_fail` tests are run as doctests, so they can only be declared in a library",
+        )
+        .to_compile_error()
+    }
+}
+
+/// Generates a benchmark with inherited properties, registering it with
+/// `demonstrate::bench_main!()` unless it was declared as `bench(nightly)`
+impl Generate for Bench {
+    fn generate(&mut self, parent_props: Option<&DescribeProps>) -> TokenStream {
+        // Inherit parent's `BlockProps` and `before`/`after` code sequences
+        if let Some(parent_props) = parent_props {
+            self.inherit(parent_props);
+        }
+
+        let BlockProps {
+            attributes,
+            is_async,
+            name,
+            path,
+            span,
+            ..
+        } = &self.properties;
+        if *is_async {
+            return syn::Error::new(*span, "Benchmarks cannot be async").to_compile_error();
+        }
+
+        let before = lease_resources(&self.properties.resources)
+            .into_iter()
+            .chain(self.before.drain(..))
+            .collect::<Vec<_>>();
+        let content = &self.content.0;
+        let after = &self.after;
+
+        // Assign bench ident based on name
+        let ident = Ident::new(&snake_case(name), Span::call_site());
+
+        if self.nightly {
+            return quote! {
+                #[bench]
+                #(#attributes)*
+                fn #ident(__bencher: &mut ::test::Bencher) {
+                    #(#before)*
+                    __bencher.iter(|| { #(#content)* });
+                    #(#after)*
+                }
+            };
+        }
+
+        quote! {
+            #(#attributes)*
+            fn #ident(__bencher: &mut ::demonstrate::Bencher) {
+                #(#before)*
+                __bencher.iter(|| { #(#content)* });
+                #(#after)*
+            }
+
+            ::demonstrate::__private::inventory::submit! {
+                ::demonstrate::__private::Benchmark {
+                    path: &[#(#path),*],
+                    module_path: module_path!(),
+                    run: #ident,
+                }
+            }
+        }
+    }
+}
+
+/// Whether an attribute is a test attribute, such as `#[tokio::test]` or
+/// `#[async_attributes::test]`
+pub(crate) fn is_test_attribute(attribute: &Attribute) -> bool {
+    attribute
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "test")
+}
+
+impl Runtime {
+    /// The runtime that a test attribute such as `#[tokio::test]` executes its test on, if it's
+    /// one that a runtime can be selected for
+    fn from_test_attribute(attribute: &Attribute) -> Option<Self> {
+        let segments = &attribute.path.segments;
+        if segments.len() != 2 {
+            return None;
+        }
+
+        // The arguments of `#[tokio::test(..)]` are those of `async(tokio, ..)`
+        let args = match attribute.tokens.clone().into_iter().next() {
+            Some(TokenTree::Group(group)) => group.stream(),
+            Some(_) => return None,
+            None => TokenStream::new(),
+        };
+        match segments[0].ident.to_string().as_str() {
+            "tokio" => syn::parse2(quote!(tokio, #args)).ok(),
+            "async_std" | "async_attributes" if args.is_empty() => Some(Runtime::AsyncStd),
+            _ => None,
+        }
+    }
+
+    /// Generates an expression that drives the given future to completion on this runtime
+    fn block_on(&self, future: TokenStream) -> TokenStream {
+        match self {
+            Runtime::Tokio {
+                multi_thread,
+                worker_threads,
+            } => {
+                let builder = if *multi_thread {
+                    let worker_threads = worker_threads
+                        .as_ref()
+                        .map(|worker_threads| quote!(.worker_threads(#worker_threads)));
+                    quote!(::tokio::runtime::Builder::new_multi_thread() #worker_threads)
+                } else {
+                    quote!(::tokio::runtime::Builder::new_current_thread())
+                };
+
+                quote! {
+                    #builder
+                        .enable_all()
+                        .build()
+                        .expect("Failed building the Tokio runtime")
+                        .block_on(#future)
+                }
+            }
+            Runtime::AsyncStd => quote!(::async_std::task::block_on(#future)),
+            Runtime::BlockOn => quote!(::demonstrate::block_on(#future)),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Emits the inherited `before` and `after` code sequences once per module, for the tests that
+//! can share them, rather than copying them into each test
+
+use crate::block::{DescribeProps, Test};
+use crate::generate::is_libtest_attribute;
+use crate::unused::{kept_indices, remove_unused};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_quote, Expr, Item, Lit, Local, Pat, Stmt};
+
+/// Generates the tests of a `Describe` block, running those that can share its `before` and
+/// `after` code sequences through a function that runs them around each test's own code
+///
+/// A helper can't return the bindings of the `before` code sequences without their types, so
+/// tests instead pass their index to a function that matches it to their code, which is then in
+/// the scope of those bindings. Tests only share a function if they're sync, aren't death tests,
+/// return the same type, have the same setup and use the same `#[lazy]` statements, so that each
+/// still runs the same statements as it would on its own. Since the bindings then have one type
+/// for all of the tests, those that the tests use must also have types that don't depend on how
+/// they're used, unless the tests' code is the same. The others copy the code sequences.
+pub(crate) fn generate_tests(tests: Vec<&mut Test>, parent_props: &DescribeProps) -> TokenStream {
+    let mut output = TokenStream::new();
+    let mut groups: Vec<(String, Vec<&mut Test>)> = Vec::new();
+    for test in tests {
+        let death_preamble = match test.prepare(Some(parent_props)) {
+            Ok(death_preamble) => death_preamble,
+            Err(error) => {
+                output.extend(error.to_compile_error());
+                continue;
+            }
+        };
+
+        match share_key(test) {
+            Some(key) => match groups.iter_mut().find(|(other, _)| *other == key) {
+                Some((_, group)) => group.push(test),
+                None => groups.push((key, vec![test])),
+            },
+            None => {
+                test.inline_hooks();
+                output.extend(test.generate_fns(death_preamble));
+            }
+        }
+    }
+
+    for (index, (_, mut group)) in groups.into_iter().enumerate() {
+        if let [test] = &mut group[..] {
+            test.inline_hooks();
+            output.extend(test.generate_fns(Vec::new()));
+        } else {
+            output.extend(share(index, group));
+        }
+    }
+
+    output
+}
+
+/// Identifies the tests that a test can share its code sequences with, if it can share them
+fn share_key(test: &Test) -> Option<String> {
+    let props = &test.properties;
+    // The code of async tests and death tests is run within a future or closure of their own,
+    // and attributes such as `#[cfg]` apply to the whole function of the test
+    if (test.before.is_empty() && test.after.is_empty())
+        || props.is_async
+        || test.is_death_test()
+        || !props
+            .attributes
+            .iter()
+            .all(|attribute| is_libtest_attribute(attribute) || attribute.path.is_ident("doc"))
+    {
+        return None;
+    }
+
+    // `#[lazy]` statements may have effects, so they're only run by the tests that use them
+    let rest = test
+        .content
+        .0
+        .iter()
+        .chain(&test.after)
+        .cloned()
+        .collect::<Vec<_>>();
+    let kept = kept_indices(&test.before, &rest)
+        .into_iter()
+        .filter_map(|index| match &test.before[index] {
+            Stmt::Local(local) => Some(local),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let lazy = kept
+        .iter()
+        .filter(|local| local.attrs.iter().any(|attr| attr.path.is_ident("lazy")))
+        .map(|local| local.pat.to_token_stream().to_string())
+        .collect::<Vec<_>>();
+
+    // Bindings whose types are inferred from their uses are only shared by the same code
+    let content = &test.content.0;
+    let code = if kept.iter().all(|local| is_typed(local)) {
+        TokenStream::new()
+    } else {
+        quote!(#(#content)*)
+    };
+
+    let setup = &test.setup;
+    let return_type = return_type(test);
+    Some(format!(
+        "{} {} {:?} {} {}",
+        quote!(#return_type),
+        shares_after(test),
+        lazy,
+        quote!(#(#setup)*),
+        code
+    ))
+}
+
+/// Whether the type of a `let` statement's bindings is written out, or is that of a literal
+/// which doesn't depend on its uses
+fn is_typed(local: &Local) -> bool {
+    if let Pat::Type(pat_type) = &local.pat {
+        let ty = pat_type.ty.to_token_stream().to_string();
+        return !ty.split_whitespace().any(|token| token == "_");
+    }
+
+    match local.init.as_ref().map(|(_, init)| &**init) {
+        Some(Expr::Lit(lit)) => match &lit.lit {
+            Lit::Str(_) | Lit::ByteStr(_) | Lit::Byte(_) | Lit::Char(_) | Lit::Bool(_) => true,
+            Lit::Int(int) => !int.suffix().is_empty(),
+            Lit::Float(float) => !float.suffix().is_empty(),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+/// The type returned by the function of a test, if it returns one
+pub(crate) fn return_type(test: &Test) -> Option<TokenStream> {
+    // The value returned by the code sequence of a test with an expectation is checked rather
+    // than returned
+    test.properties
+        .return_type
+        .as_ref()
+        .filter(|_| test.expectation.is_none())
+        .map(ToTokens::to_token_stream)
+}
+
+/// Whether the `after` code sequences can run after the match on a test's code, once everything
+/// that its code binds has been dropped
+///
+/// That's only the case when its code is its own code sequence, without the guards of process
+/// state or fixtures or an outcome to check, and that binds nothing which would otherwise be
+/// dropped after the `after` code sequences.
+fn shares_after(test: &Test) -> bool {
+    let props = &test.properties;
+    test.expectation.is_none()
+        && props.env.is_empty()
+        && props.dirs.is_empty()
+        && props.fixtures.is_empty()
+        && test.content.0.iter().all(binds_nothing)
+}
+
+/// Whether a statement binds nothing in the scope of the code sequence containing it
+///
+/// Macros other than those of the standard library that only evaluate their arguments may
+/// expand to `let` statements.
+fn binds_nothing(stmt: &Stmt) -> bool {
+    const MACROS: &[&str] = &[
+        "assert",
+        "assert_eq",
+        "assert_ne",
+        "debug_assert",
+        "debug_assert_eq",
+        "debug_assert_ne",
+        "print",
+        "println",
+        "eprint",
+        "eprintln",
+        "panic",
+        "unreachable",
+        "todo",
+        "unimplemented",
+        "write",
+        "writeln",
+        "dbg",
+    ];
+    let is_std = |path: &syn::Path| MACROS.iter().any(|name| path.is_ident(name));
+    match stmt {
+        Stmt::Local(_) => false,
+        Stmt::Item(Item::Macro(item)) => item.ident.is_none() && is_std(&item.mac.path),
+        Stmt::Item(_) => true,
+        Stmt::Expr(Expr::Macro(expr)) | Stmt::Semi(Expr::Macro(expr), _) => is_std(&expr.mac.path),
+        Stmt::Expr(_) | Stmt::Semi(..) => true,
+    }
+}
+
+/// Generates the function that runs the setup and code sequences shared by the given tests
+/// around the code of the test with the given index, along with the tests calling it
+fn share(index: usize, mut tests: Vec<&mut Test>) -> TokenStream {
+    let hooks = format_ident!("__demonstrate_hooks_{}", index);
+    let setup = std::mem::take(&mut tests[0].setup);
+    let return_tokens = return_type(tests[0]).map(|return_type| quote!(-> #return_type));
+
+    // The `after` code sequences are run once the test's code has finished, unless what its code
+    // binds has to outlive them
+    let after = if shares_after(tests[0]) {
+        let after = std::mem::take(&mut tests[0].after);
+        tests.iter_mut().for_each(|test| test.after.clear());
+        after
+    } else {
+        Vec::new()
+    };
+    let returns = return_tokens.is_some();
+    let arms = tests
+        .iter_mut()
+        .map(|test| {
+            let code = std::mem::take(&mut test.content.0);
+            then_after(code, std::mem::take(&mut test.after), returns)
+        })
+        .collect::<Vec<_>>();
+
+    // The `before` code sequences are shared, so they keep what any of the tests use
+    let rest = arms
+        .iter()
+        .flatten()
+        .chain(&after)
+        .cloned()
+        .collect::<Vec<_>>();
+    let before = remove_unused(&tests[0].before, &rest);
+
+    let indices = 0..arms.len();
+    let run = parse_quote! {
+        match __demonstrate_test {
+            #(#indices => { #(#arms)* })*
+            _ => ::std::unreachable!(),
+        }
+    };
+    let body = then_after(vec![Stmt::Expr(run)], after, returns);
+
+    let tests = tests
+        .into_iter()
+        .enumerate()
+        .map(|(index, test)| {
+            test.content.0 = vec![Stmt::Expr(parse_quote!(#hooks(#index)))];
+            test.generate_fns(Vec::new())
+        })
+        .collect::<TokenStream>();
+
+    quote! {
+        fn #hooks(__demonstrate_test: usize) #return_tokens {
+            #(#setup)*
+            #(#before)*
+            #(#body)*
+        }
+
+        #tests
+    }
+}
+
+/// Appends the `after` code sequences to the code of a test, keeping the value of its code unless
+/// the `after` code sequences have one of their own
+///
+/// The value is bound in the scope of the code rather than a block of its own, so that the rest
+/// of what the code binds is still dropped after the `after` code sequences.
+pub(crate) fn then_after(mut code: Vec<Stmt>, mut after: Vec<Stmt>, returns: bool) -> Vec<Stmt> {
+    if after.is_empty() {
+        return code;
+    }
+
+    if let Some(Stmt::Expr(_)) = code.last() {
+        let value = match code.pop() {
+            Some(Stmt::Expr(value)) => value,
+            _ => unreachable!(),
+        };
+        if !returns || matches!(after.last(), Some(Stmt::Expr(_))) {
+            code.push(Stmt::Semi(value, Default::default()));
+        } else {
+            code.push(parse_quote!(let __demonstrate_value = #value;));
+            after.push(Stmt::Expr(parse_quote!(__demonstrate_value)));
+        }
+    }
+
+    code.append(&mut after);
+    code
+}
@@ -0,0 +1,488 @@
+//! Defines the inheritance behavior of `Describe` and `Test` block properties
+
+use crate::block::{
+    BasicBlock, Bench, BlockProps, CompileFail, Describe, DescribeProps, Dir, Expectation,
+    NoInherit, SharedState, StateField, Test,
+};
+use crate::fixture::{lease_stmts, merge_requests, set_up_stmts, shared_fns};
+use crate::generate::is_test_attribute;
+use crate::unused::remove_unused;
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_quote, Attribute, Ident, Stmt};
+
+/// The trait and respective function for inheriting the parent `Describe` block's properties
+pub(crate) trait Inherit {
+    fn inherit(&mut self, parent_props: &DescribeProps);
+}
+
+impl Inherit for Describe {
+    fn inherit(&mut self, parent_props: &DescribeProps) {
+        // Inherit the `BlockProps` shared with `Test` blocks
+        self.properties.block_props.inherit(parent_props);
+
+        // Inherit the state set up by ancestoral `before_all` blocks
+        self.properties.shared = parent_props.shared.clone();
+
+        // Inherit the fixtures declared by ancestors
+        self.properties.fixtures = parent_props.fixtures.clone();
+
+        // Prepend the fields of the context declared by ancestors
+        self.properties.state = parent_props
+            .state
+            .iter()
+            .chain(self.properties.state.iter())
+            .cloned()
+            .collect();
+
+        // Inherit `before` code sequences from parent
+        if let Some(ref parent_props_before) = &parent_props.before {
+            // Prepend parent_props's `before` code sequence
+            let before = if let Some(ref self_before) = &self.properties.before {
+                parent_props_before
+                    .0
+                    .iter()
+                    .chain(self_before.0.iter())
+                    .cloned()
+                    .collect()
+            } else {
+                parent_props_before.0.clone()
+            };
+
+            self.properties.before = Some(BasicBlock(before));
+        }
+
+        // Inherit `after` code sequences from parent
+        if let Some(ref parent_props_after) = &parent_props.after {
+            // Append parent_props's `after` code sequence
+            if let Some(ref mut self_after) = &mut self.properties.after {
+                self_after.0.extend(parent_props_after.0.clone());
+            } else {
+                self.properties.after = Some(parent_props_after.clone());
+            }
+        }
+    }
+}
+
+impl Inherit for Test {
+    fn inherit(&mut self, parent_props: &DescribeProps) {
+        // Inherit the `BlockProps` shared with `Describe` blocks
+        self.properties.inherit(parent_props);
+
+        // Catch the outcome of the test's own code sequence, which is checked against its
+        // expectation once the `after` code sequences have run
+        if let Some(catch) = self
+            .expectation
+            .as_ref()
+            .and_then(|expectation| expectation.catch(&self.content, &self.properties))
+        {
+            self.content = BasicBlock(vec![catch]);
+        }
+
+        // Set the process state and then the requested fixtures up after the `before` code
+        // sequences, for the rest of the test
+        let mut stmts = self.properties.process_state_stmts();
+        stmts.extend(fixture_stmts(&self.properties, parent_props));
+        self.content.0.splice(0..0, stmts);
+        self.leases.extend(lease_stmts(
+            &self.properties.fixtures,
+            &parent_props.fixtures,
+        ));
+
+        // Keep the `after` code sequence from parent and the check of the expectation apart, so
+        // that tests can share them
+        if let Some(ref parent_props_after) = &parent_props.after {
+            self.after = parent_props_after.0.clone();
+        }
+        self.after
+            .extend(self.expectation.as_ref().and_then(Expectation::check));
+
+        // Keep the `before` code sequence from parent apart as well, since the parts of it that
+        // are used depend on the tests sharing it
+        if let Some(ref parent_props_before) = &parent_props.before {
+            self.before = parent_props_before.0.clone();
+        }
+
+        // Set the state of ancestoral `before_all` blocks up and create the context before the
+        // `before` code sequences, which can use and modify them
+        self.setup = hook_timings_stmt(parent_props)
+            .into_iter()
+            .chain(
+                parent_props
+                    .shared
+                    .iter()
+                    .flat_map(SharedState::set_up_stmts),
+            )
+            .chain(context_stmt(&parent_props.state))
+            .collect();
+
+        // Lease the state of ancestoral `before_all` blocks before anything else, so that it's
+        // leased before the test's preamble, which can skip it
+        self.leases.splice(
+            0..0,
+            parent_props.shared.iter().map(SharedState::lease_stmt),
+        );
+        self.shared = parent_props
+            .shared
+            .iter()
+            .map(SharedState::shared_fn)
+            .chain(shared_fns(
+                &self.properties.fixtures,
+                &parent_props.fixtures,
+            ))
+            .collect();
+    }
+}
+
+impl Expectation {
+    /// Generates the statement that runs a test's own code sequence, catching its outcome
+    ///
+    /// Death tests are checked by the process running them instead, so they aren't caught.
+    fn catch(&self, content: &BasicBlock, props: &BlockProps) -> Option<Stmt> {
+        if let Expectation::Death(_) = self {
+            return None;
+        }
+        let content = &content.0;
+        let return_type = props
+            .return_type
+            .as_ref()
+            .map_or_else(|| quote!(()), ToTokens::to_token_stream);
+        Some(if props.is_async {
+            parse_quote! {
+                let __demonstrate_outcome = ::demonstrate::__private::catch_unwind_async::<
+                    #return_type,
+                    _,
+                >(async { #(#content)* })
+                .await;
+            }
+        } else {
+            parse_quote! {
+                let __demonstrate_outcome =
+                    ::demonstrate::__private::catch_unwind(|| -> #return_type { #(#content)* });
+            }
+        })
+    }
+
+    /// Generates the statement that fails the test unless its outcome was the expected one
+    fn check(&self) -> Option<Stmt> {
+        Some(match self {
+            Expectation::Panic(expected) => parse_quote! {
+                ::demonstrate::__private::expect_panic(__demonstrate_outcome, #expected);
+            },
+            Expectation::Error(pattern) => {
+                let pattern_string = pattern.to_token_stream().to_string().replace(" :: ", "::");
+                parse_quote! {
+                    match __demonstrate_outcome {
+                        ::std::result::Result::Ok(::std::result::Result::Err(#pattern)) => {}
+                        ::std::result::Result::Ok(result) => {
+                            ::demonstrate::__private::unexpected_result(result, #pattern_string)
+                        }
+                        ::std::result::Result::Err(payload) => ::std::panic::resume_unwind(payload),
+                    }
+                }
+            }
+            Expectation::Death(_) => return None,
+        })
+    }
+}
+
+impl SharedState {
+    /// The generated function returning this state, which its lease counts the users of
+    pub(crate) fn shared_fn(&self) -> Ident {
+        format_ident!("{}_shared", self.lease)
+    }
+
+    /// Generates the statement that leases this state for a test
+    fn lease_stmt(&self) -> Stmt {
+        let lease_fn = &self.lease;
+        let lease = format_ident!("{}_lease", lease_fn);
+
+        parse_quote!(let #lease = #lease_fn();)
+    }
+
+    /// Generates the statements that set this state up for a test and bind its shared fields
+    fn set_up_stmts(&self) -> Vec<Stmt> {
+        let lease = format_ident!("{}_lease", self.lease);
+
+        let mut stmts: Vec<Stmt> = vec![parse_quote!(#lease.set_up();)];
+        stmts.extend(self.fields.iter().map(|field| -> Stmt {
+            parse_quote! {
+                #[allow(unused_variables)]
+                let #field = &#lease.#field;
+            }
+        }));
+
+        stmts
+    }
+}
+
+impl Inherit for CompileFail {
+    fn inherit(&mut self, parent_props: &DescribeProps) {
+        // The snippet is compiled on its own, so only the names and tags of ancestors are
+        // inherited, for the exported manifests
+        let parent_props = &parent_props.block_props;
+        self.properties.path = parent_props
+            .path
+            .iter()
+            .chain(self.properties.path.iter())
+            .cloned()
+            .collect();
+        self.properties.tags = parent_props
+            .tags
+            .iter()
+            .chain(self.properties.tags.iter())
+            .cloned()
+            .collect();
+    }
+}
+
+impl Inherit for Bench {
+    fn inherit(&mut self, parent_props: &DescribeProps) {
+        // Inherit the `BlockProps` shared with `Describe` blocks
+        self.properties.inherit(parent_props);
+
+        // Keep the `before` and `after` code sequences from parent outside of the timed region
+        if let Some(ref parent_props_after) = &parent_props.after {
+            self.after = parent_props_after.0.clone();
+        }
+        let mut setup = lease_stmts(&self.properties.fixtures, &parent_props.fixtures);
+        setup.extend(self.properties.process_state_stmts());
+        setup.extend(fixture_stmts(&self.properties, parent_props));
+
+        self.before.extend(hook_timings_stmt(parent_props));
+        self.before.extend(context_stmt(&parent_props.state));
+        if let Some(ref parent_props_before) = &parent_props.before {
+            let rest = setup
+                .iter()
+                .chain(self.content.0.iter())
+                .chain(self.after.iter())
+                .cloned()
+                .collect::<Vec<_>>();
+            self.before
+                .extend(remove_unused(&parent_props_before.0, &rest));
+        }
+        self.before.extend(setup);
+    }
+}
+
+impl Inherit for BlockProps {
+    fn inherit(&mut self, parent_props: &DescribeProps) {
+        // Prepend the names of ancestors
+        self.path = parent_props
+            .block_props
+            .path
+            .iter()
+            .chain(self.path.iter())
+            .cloned()
+            .collect();
+
+        // Prepend the attributes from parent that self inherits, once each
+        let mut attributes = parent_props
+            .block_props
+            .attributes
+            .iter()
+            .filter(|attribute| self.inherits(attribute))
+            .cloned()
+            .collect::<Vec<_>>();
+        for attribute in self.attributes.drain(..) {
+            let tokens = attribute.to_token_stream().to_string();
+            if attributes
+                .iter()
+                .all(|other| other.to_token_stream().to_string() != tokens)
+            {
+                attributes.push(attribute);
+            }
+        }
+        self.attributes = attributes;
+
+        // Prepend tags from parent
+        self.tags = parent_props
+            .block_props
+            .tags
+            .iter()
+            .chain(self.tags.iter())
+            .cloned()
+            .collect();
+
+        // Prepend the environment variables required by the parent
+        self.required_env = parent_props
+            .block_props
+            .required_env
+            .iter()
+            .chain(self.required_env.iter())
+            .cloned()
+            .collect();
+
+        // If self isn't expected to fail for a reason of its own, it is for its parent's
+        if self.xfail.is_none() {
+            self.xfail = parent_props.block_props.xfail.clone();
+        }
+
+        // Hold the parent's serial locks as well, in a consistent order to avoid deadlocks
+        self.serial
+            .extend(parent_props.block_props.serial.iter().cloned());
+        self.serial.sort();
+        self.serial.dedup();
+
+        // Prepend the resources used by the parent, leasing each only once since a test holding
+        // two leases of a pooled resource could wait on itself
+        let mut resources = parent_props.block_props.resources.clone();
+        for resource in self.resources.drain(..) {
+            let tokens = resource.to_token_stream().to_string();
+            if resources
+                .iter()
+                .all(|parent| parent.to_token_stream().to_string() != tokens)
+            {
+                resources.push(resource);
+            }
+        }
+        self.resources = resources;
+
+        // Set the parent's environment variables and change into its directories first, so that
+        // self can override the former and change into directories relative to the latter
+        self.env = parent_props
+            .block_props
+            .env
+            .iter()
+            .chain(self.env.iter())
+            .cloned()
+            .collect();
+        self.dirs = parent_props
+            .block_props
+            .dirs
+            .iter()
+            .chain(self.dirs.iter())
+            .cloned()
+            .collect();
+
+        // Request the parent's fixtures as well
+        self.fixtures = merge_requests(&parent_props.block_props.fixtures, &self.fixtures);
+
+        // If parent is async, so is self
+        if !self.is_async && parent_props.block_props.is_async {
+            self.is_async = true;
+        }
+
+        // If self doesn't have a runtime, use its parent's
+        if self.runtime.is_none() {
+            self.runtime = parent_props.block_props.runtime.clone()
+        }
+
+        // If self doesn't have a return type, use its parent's
+        if self.return_type.is_none() {
+            self.return_type = parent_props.block_props.return_type.clone()
+        }
+    }
+}
+
+/// Generates the statement that binds the timings recorded by the inherited hooks, if any
+///
+/// It comes first, so that the timings are those of the thread running the test even when an
+/// async runtime resumes its hooks on other threads.
+fn hook_timings_stmt(parent_props: &DescribeProps) -> Option<Stmt> {
+    if parent_props.before.is_none() && parent_props.after.is_none() {
+        return None;
+    }
+
+    Some(parse_quote! {
+        let __demonstrate_hook_timings = ::demonstrate::__private::HookTimings::current();
+    })
+}
+
+/// Generates the statement that binds the context declared by `state` blocks to `ctx`, if any
+/// fields were declared
+fn context_stmt(state: &[StateField]) -> Option<Stmt> {
+    if state.is_empty() {
+        return None;
+    }
+
+    let fields = state.iter().map(|field| &field.name);
+    let defaults = state.iter().map(|field| &field.default);
+    Some(parse_quote! {
+        #[allow(unused_variables, unused_mut)]
+        let mut ctx = __DemonstrateContext {
+            #(#fields: #defaults,)*
+        };
+    })
+}
+
+/// Generates the statements that set up the fixtures requested by a test or benchmark, or a
+/// compile error if they can't be resolved
+fn fixture_stmts(props: &BlockProps, parent_props: &DescribeProps) -> Vec<Stmt> {
+    set_up_stmts(&props.fixtures, &parent_props.fixtures).unwrap_or_else(|error| {
+        let error = error.to_compile_error();
+        vec![parse_quote!(#error;)]
+    })
+}
+
+impl BlockProps {
+    /// Generates the statements that set the process state declared with `with_env`, `in_dir`
+    /// and `in_tempdir`, binding guards that restore it once the test has finished
+    fn process_state_stmts(&self) -> Vec<Stmt> {
+        let mut stmts = Vec::new();
+        for (index, dir) in self.dirs.iter().enumerate() {
+            let guard = format_ident!("__demonstrate_dir_{}", index);
+            match dir {
+                Dir::Path(path) => stmts.push(parse_quote! {
+                    let #guard = ::demonstrate::__private::CurrentDir::change(#path);
+                }),
+                Dir::Temp => {
+                    stmts.push(parse_quote! {
+                        let #guard = ::demonstrate::__private::CurrentDir::temporary();
+                    });
+                    stmts.push(parse_quote! {
+                        #[allow(unused_variables)]
+                        let tempdir = #guard.path();
+                    });
+                }
+            }
+        }
+
+        if !self.env.is_empty() {
+            let (variables, values): (Vec<_>, Vec<_>) = self.env.iter().cloned().unzip();
+            stmts.push(parse_quote! {
+                let __demonstrate_env = ::demonstrate::__private::Env::set(vec![
+                    #((#variables, ::std::ffi::OsString::from(#values))),*
+                ]);
+            });
+        }
+
+        stmts
+    }
+}
+
+impl BlockProps {
+    /// Whether this block inherits an attribute of its parent, which it doesn't if it opted out
+    /// of it with `#[no_inherit]` or overrides it with an attribute that can only be applied once
+    fn inherits(&self, attribute: &Attribute) -> bool {
+        self.no_inherit
+            .inherits(&attribute.path.to_token_stream().to_string())
+            && !self
+                .attributes
+                .iter()
+                .any(|other| overrides(other, attribute))
+    }
+}
+
+impl NoInherit {
+    /// Whether the attributes with the given path are inherited
+    pub(crate) fn inherits(&self, path: &str) -> bool {
+        match self {
+            NoInherit::All => false,
+            NoInherit::Paths(paths) => paths
+                .iter()
+                .all(|other| other.to_token_stream().to_string() != path),
+        }
+    }
+}
+
+/// Whether an attribute overrides an inherited one, since a test can only have one of them
+fn overrides(attribute: &Attribute, inherited: &Attribute) -> bool {
+    let is_exclusive = |attribute: &Attribute| {
+        attribute.path.is_ident("should_panic") || attribute.path.is_ident("ignore")
+    };
+
+    (is_test_attribute(attribute) && is_test_attribute(inherited))
+        || (is_exclusive(attribute)
+            && attribute.path.to_token_stream().to_string()
+                == inherited.path.to_token_stream().to_string())
+}
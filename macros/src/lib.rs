@@ -0,0 +1,37 @@
+//! Procedural macros for the `demonstrate` testing framework
+//!
+//! These are re-exported by the `demonstrate` crate, which should be depended upon instead as it
+//! also provides the runtime support used by the generated code.
+
+extern crate proc_macro;
+
+use crate::block::Root;
+use crate::generate::Generate;
+
+mod block;
+mod docs;
+mod export;
+mod fixture;
+mod generate;
+mod hooks;
+mod inherit;
+mod manifest;
+mod unused;
+
+#[proc_macro]
+pub fn demonstrate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = proc_macro2::TokenStream::from(input);
+
+    let output = match syn::parse2::<Root>(input) {
+        Ok(mut root) => {
+            let docs = docs::export(&root);
+            let mut output = root.generate(None);
+            output.extend(docs);
+            output.extend(manifest::export(&root));
+            output
+        }
+        Err(error) => error.to_compile_error(),
+    };
+
+    proc_macro::TokenStream::from(output)
+}
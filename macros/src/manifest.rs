@@ -0,0 +1,189 @@
+//! Exports a JSON manifest of the tests and benchmarks generated by `demonstrate!` instances, so
+//! that tools can map test paths back to their descriptions and source locations
+
+use crate::block::{Block, BlockProps, Describe, Root};
+use crate::export::{file_name, write_files};
+use crate::generate::is_ignored;
+use proc_macro2::{Span, TokenStream};
+use quote::ToTokens;
+use std::collections::HashSet;
+use voca_rs::case::snake_case;
+
+/// The environment variable naming the directory that manifests are exported to, which is
+/// relative to the manifest directory of the crate being compiled
+const MANIFEST_DIR_VAR: &str = "DEMONSTRATE_MANIFEST_DIR";
+
+/// Writes a JSON manifest for each root `Describe` block to the directory named by
+/// `DEMONSTRATE_MANIFEST_DIR`, if it is set
+///
+/// This expects the blocks to have already been generated, so that they have inherited the
+/// properties of their ancestors.
+pub(crate) fn export(root: &Root) -> TokenStream {
+    write_files(MANIFEST_DIR_VAR, "the manifest", || {
+        root.0
+            .iter()
+            .map(|describe| {
+                let name = file_name(&describe.properties.block_props.name, "json");
+                (name, manifest(describe))
+            })
+            .collect()
+    })
+}
+
+/// The manifest of a root `Describe` block
+fn manifest(describe: &Describe) -> String {
+    let mut entries = Vec::new();
+    collect_entries(&describe.blocks, &mut entries);
+
+    format!(
+        "{{\n  \"source_file\": {},\n  \"tests\": [\n{}\n  ]\n}}\n",
+        json_string(&Span::call_site().file()),
+        entries.join(",\n"),
+    )
+}
+
+/// Collects the manifest entries of the tests and benchmarks within the given blocks
+fn collect_entries(blocks: &[Block], entries: &mut Vec<String>) {
+    for block in blocks {
+        match block {
+            Block::Describe(describe) => collect_entries(&describe.blocks, entries),
+            Block::Test(test) => entries.push(entry("test", &test.properties)),
+            Block::CompileFail(compile_fail) => {
+                entries.push(entry("compile_fail", &compile_fail.properties))
+            }
+            Block::Bench(bench) => entries.push(entry("bench", &bench.properties)),
+        }
+    }
+}
+
+/// The manifest entry of a test or benchmark
+fn entry(kind: &str, block_props: &BlockProps) -> String {
+    let start = block_props.span.start();
+
+    let description = block_props
+        .path
+        .iter()
+        .map(|name| json_string(name))
+        .collect::<Vec<_>>();
+    // Relative to the module that the `demonstrate!` macro was called in
+    let test_path = block_props
+        .path
+        .iter()
+        .map(|name| snake_case(name))
+        .collect::<Vec<_>>()
+        .join("::");
+    // Tags are inherited, so a test can repeat those of any ancestor
+    let mut seen = HashSet::new();
+    let tags = block_props
+        .tags
+        .iter()
+        .map(|tag| json_string(&tag.value()))
+        .filter(|tag| seen.insert(tag.clone()))
+        .collect::<Vec<_>>();
+    let return_type = block_props
+        .return_type
+        .as_ref()
+        .map_or_else(|| "null".to_owned(), |ty| json_string(&type_string(ty)));
+
+    format!(
+        "    {{\"kind\": {}, \"description\": [{}], \"test_path\": {}, \"line\": {}, \
+         \"column\": {}, \"tags\": [{}], \"async\": {}, \"ignored\": {}, \"return_type\": {}}}",
+        json_string(kind),
+        description.join(", "),
+        json_string(&test_path),
+        start.line,
+        start.column + 1,
+        tags.join(", "),
+        block_props.is_async,
+        is_ignored(block_props),
+        return_type,
+    )
+}
+
+/// Displays a type the way it would usually be written, rather than with spaces between tokens
+fn type_string(ty: &syn::Type) -> String {
+    ty.to_token_stream()
+        .to_string()
+        .replace(" < ", "<")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+        .replace(" >", ">")
+}
+
+/// Encodes a string as a JSON string literal, escaping it the same way as the runner's JSON
+/// reporter so that descriptions can be matched between the manifest and the reported events
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Generate;
+
+    #[test]
+    fn lists_the_tests_with_their_locations() {
+        let mut root = syn::parse_str::<Root>(
+            r#"
+#[tag("unit")]
+describe "parser" {
+    #[ignore]
+    #[tag("unit", "fast")]
+    it "parses \"digits\"" -> Result<Vec<u32>, String> {
+        Ok(vec![4])
+    }
+
+    #[tag("slow")]
+    async(block_on) context "with signs" {
+        #[tag("unit")]
+        it "parses negative numbers" {}
+
+        bench "parses quickly" {}
+    }
+
+    it "rejects strings" compile_fail {
+        let number: u32 = "4";
+    }
+}
+"#,
+        )
+        .unwrap();
+        root.generate(None);
+
+        assert_eq!(
+            manifest(&root.0[0]),
+            r#"{
+  "source_file": "<unspecified>",
+  "tests": [
+    {"kind": "test", "description": ["parser", "parses \"digits\""], "test_path": "parser::parses_digits", "line": 6, "column": 8, "tags": ["unit", "fast"], "async": false, "ignored": true, "return_type": "Result<Vec<u32>, String>"},
+    {"kind": "test", "description": ["parser", "with signs", "parses negative numbers"], "test_path": "parser::with_signs::parses_negative_numbers", "line": 13, "column": 12, "tags": ["unit", "slow"], "async": true, "ignored": false, "return_type": null},
+    {"kind": "bench", "description": ["parser", "with signs", "parses quickly"], "test_path": "parser::with_signs::parses_quickly", "line": 15, "column": 15, "tags": ["unit", "slow"], "async": true, "ignored": false, "return_type": null},
+    {"kind": "compile_fail", "description": ["parser", "rejects strings"], "test_path": "parser::rejects_strings", "line": 18, "column": 8, "tags": ["unit"], "async": false, "ignored": false, "return_type": null}
+  ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn escapes_strings_like_the_json_reporter() {
+        assert_eq!(
+            json_string("\"4\" \\ 5\n\r\t\u{1}\u{7f}é"),
+            r#""\"4\" \\ 5\n\r\t\u0001\u007fé""#
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Removes the `let` statements of inherited `before` blocks that a test doesn't use, so that
+//! tests don't warn about or execute setup that only their siblings need
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use std::collections::HashSet;
+use syn::{Attribute, Expr, Local, Pat, Stmt};
+
+/// Removes the `let` statements from the given `before` code sequence whose bindings aren't used
+/// by the statements following them, either directly or through other statements that are kept
+///
+/// Only statements without side effects are removed, unless they are marked with `#[lazy]`,
+/// which is removed from the statements that are kept. Statements binding names starting with
+/// `_` are kept regardless, as they're only held to be dropped at the end of the test.
+pub(crate) fn remove_unused(before: &[Stmt], rest: &[Stmt]) -> Vec<Stmt> {
+    kept(before, rest)
+        .into_iter()
+        .map(|(_, stmt)| stmt)
+        .collect()
+}
+
+/// The indices of the statements that `remove_unused` keeps
+pub(crate) fn kept_indices(before: &[Stmt], rest: &[Stmt]) -> Vec<usize> {
+    kept(before, rest)
+        .into_iter()
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// The statements that `remove_unused` keeps, along with their indices
+fn kept(before: &[Stmt], rest: &[Stmt]) -> Vec<(usize, Stmt)> {
+    let mut used = HashSet::new();
+    for stmt in rest {
+        collect_idents(stmt.to_token_stream(), &mut used);
+    }
+
+    let mut kept = Vec::with_capacity(before.len());
+    for (index, stmt) in before.iter().enumerate().rev() {
+        let mut stmt = stmt.clone();
+        if let Stmt::Local(local) = &mut stmt {
+            let lazy = take_lazy(&mut local.attrs);
+            if (lazy || is_pure(local)) && !binds_used(local, &used) && !is_held(&local.pat) {
+                continue;
+            }
+        }
+
+        collect_idents(stmt.to_token_stream(), &mut used);
+        kept.push((index, stmt));
+    }
+    kept.reverse();
+
+    kept
+}
+
+/// Removes the `#[lazy]` attribute, returning whether it was present
+fn take_lazy(attributes: &mut Vec<Attribute>) -> bool {
+    let count = attributes.len();
+    attributes.retain(|attribute| !attribute.path.is_ident("lazy"));
+
+    attributes.len() < count
+}
+
+/// Whether any name in the pattern of a `let` statement is used
+///
+/// Every identifier in the pattern is considered, including the paths of structs and enum
+/// variants, which only errs on the side of keeping the statement.
+fn binds_used(local: &Local, used: &HashSet<String>) -> bool {
+    let mut bound = HashSet::new();
+    collect_idents(local.pat.to_token_stream(), &mut bound);
+
+    bound.iter().any(|name| used.contains(name))
+}
+
+/// Whether a pattern starts with `_`, as deliberately unused bindings do
+fn is_held(pat: &Pat) -> bool {
+    match pat {
+        Pat::Wild(_) => true,
+        Pat::Ident(pat_ident) => pat_ident.ident.to_string().starts_with('_'),
+        Pat::Type(pat_type) => is_held(&pat_type.pat),
+        _ => false,
+    }
+}
+
+/// Collects the identifiers within the tokens, including any words within literals since format
+/// strings can capture variables
+fn collect_idents(tokens: TokenStream, idents: &mut HashSet<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            TokenTree::Literal(literal) => idents.extend(
+                literal
+                    .to_string()
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_owned),
+            ),
+            TokenTree::Punct(_) => {}
+        }
+    }
+}
+
+/// Whether a `let` statement has no side effects besides binding its value
+fn is_pure(local: &Local) -> bool {
+    local
+        .init
+        .as_ref()
+        .map_or(true, |(_, init)| is_pure_expr(init))
+}
+
+/// Whether an expression has no side effects, considering function and method calls along with
+/// most macros to have them
+///
+/// Paths and struct literals may construct or move guards whose drop has effects, while operators
+/// and indexing may be overloaded, so only borrowing a path is considered pure.
+fn is_pure_expr(expr: &Expr) -> bool {
+    match expr {
+        Expr::Lit(_) | Expr::Closure(_) => true,
+        Expr::Reference(reference) => is_place(&reference.expr) || is_pure_expr(&reference.expr),
+        Expr::Paren(paren) => is_pure_expr(&paren.expr),
+        Expr::Group(group) => is_pure_expr(&group.expr),
+        Expr::Cast(cast) => is_pure_expr(&cast.expr),
+        Expr::Tuple(tuple) => tuple.elems.iter().all(is_pure_expr),
+        Expr::Array(array) => array.elems.iter().all(is_pure_expr),
+        Expr::Repeat(repeat) => is_pure_expr(&repeat.expr),
+        // These only allocate and format their arguments, which are themselves expressions, and
+        // `format!` only borrows its arguments
+        Expr::Macro(expr_macro)
+            if expr_macro.mac.path.is_ident("vec") || expr_macro.mac.path.is_ident("format") =>
+        {
+            let borrows = expr_macro.mac.path.is_ident("format");
+            expr_macro
+                .mac
+                .parse_body_with(
+                    syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated,
+                )
+                .is_ok_and(|args| {
+                    args.iter()
+                        .all(|arg| (borrows && is_place(arg)) || is_pure_expr(arg))
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Whether an expression is a variable or one of its fields, which can be borrowed without
+/// effects
+fn is_place(expr: &Expr) -> bool {
+    match expr {
+        Expr::Path(_) => true,
+        Expr::Field(field) => is_place(&field.base),
+        Expr::Paren(paren) => is_place(&paren.expr),
+        _ => false,
+    }
+}
@@ -0,0 +1,206 @@
+//! Benchmarks declared with `bench` blocks, along with the runner used by
+//! `demonstrate::bench_main!()`
+
+use crate::skip::skipped_reason;
+use std::fmt;
+use std::hint::black_box;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::time::{Duration, Instant};
+
+/// A benchmark registered by a `bench` block
+pub struct Benchmark {
+    /// The names of the benchmark's ancestoral blocks followed by its own
+    pub path: &'static [&'static str],
+    /// The module path of the generated benchmark function
+    pub module_path: &'static str,
+    /// The generated benchmark function
+    pub run: fn(&mut Bencher),
+}
+
+inventory::collect!(Benchmark);
+
+/// Times the code sequence of a `bench` block
+///
+/// The code sequence is executed repeatedly for a warm-up period, which is used to estimate how
+/// many iterations fit into each sample of the measurement period.
+pub struct Bencher {
+    /// The measurement settings, or `None` if the code sequence should only be executed once
+    settings: Option<Settings>,
+    /// The summary of the samples taken by the last call to `iter`
+    summary: Option<Summary>,
+}
+
+/// The measurement settings of a `Bencher`
+struct Settings {
+    warm_up: Duration,
+    measurement: Duration,
+    samples: usize,
+}
+
+impl Bencher {
+    /// Creates a `Bencher` that measures the code sequence
+    fn measure() -> Self {
+        Bencher {
+            settings: Some(Settings {
+                warm_up: Duration::from_millis(500),
+                measurement: Duration::from_secs(2),
+                samples: 50,
+            }),
+            summary: None,
+        }
+    }
+
+    /// Creates a `Bencher` that only executes the code sequence once to check that it succeeds
+    fn once() -> Self {
+        Bencher {
+            settings: None,
+            summary: None,
+        }
+    }
+
+    /// Times the given routine, which is the code sequence of a `bench` block
+    pub fn iter<T, F: FnMut() -> T>(&mut self, mut routine: F) {
+        let settings = match &self.settings {
+            Some(settings) => settings,
+            None => {
+                black_box(routine());
+                return;
+            }
+        };
+
+        // Warm up while estimating the duration of each iteration
+        let start = Instant::now();
+        let mut warm_up_iterations = 0u64;
+        while start.elapsed() < settings.warm_up {
+            black_box(routine());
+            warm_up_iterations += 1;
+        }
+        let estimate = start.elapsed().as_nanos() as f64 / warm_up_iterations as f64;
+        let sample_duration = settings.measurement.as_nanos() as f64 / settings.samples as f64;
+        let iterations = (sample_duration / estimate).ceil().max(1.0) as u64;
+
+        let samples = (0..settings.samples)
+            .map(|_| {
+                let start = Instant::now();
+                for _ in 0..iterations {
+                    black_box(routine());
+                }
+                start.elapsed().as_nanos() as f64 / iterations as f64
+            })
+            .collect();
+
+        self.summary = Some(Summary::new(samples, iterations));
+    }
+}
+
+/// The statistics of the nanoseconds taken per iteration in each sample
+struct Summary {
+    mean: f64,
+    median: f64,
+    std_dev: f64,
+    samples: usize,
+    iterations: u64,
+}
+
+impl Summary {
+    fn new(mut samples: Vec<f64>, iterations: u64) -> Self {
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let count = samples.len();
+        let mean = samples.iter().sum::<f64>() / count as f64;
+        let median = if count % 2 == 0 {
+            (samples[count / 2 - 1] + samples[count / 2]) / 2.0
+        } else {
+            samples[count / 2]
+        };
+        let variance = samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / (count - 1).max(1) as f64;
+
+        Summary {
+            mean,
+            median,
+            std_dev: variance.sqrt(),
+            samples: count,
+            iterations,
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mean {}, median {}, std. dev. {} ({} samples of {} iterations)",
+            Nanoseconds(self.mean),
+            Nanoseconds(self.median),
+            Nanoseconds(self.std_dev),
+            self.samples,
+            self.iterations,
+        )
+    }
+}
+
+/// Displays nanoseconds in the most readable unit
+struct Nanoseconds(f64);
+
+impl fmt::Display for Nanoseconds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (value, unit) = match self.0 {
+            ns if ns < 1e3 => (ns, "ns"),
+            ns if ns < 1e6 => (ns / 1e3, "µs"),
+            ns if ns < 1e9 => (ns / 1e6, "ms"),
+            ns => (ns / 1e9, "s"),
+        };
+        write!(f, "{:.3} {}", value, unit)
+    }
+}
+
+/// Runs the registered benchmarks whose names contain the first free argument
+///
+/// Like the libtest harness, each benchmark is only executed once to check that it succeeds
+/// unless the `--bench` flag is passed, which `cargo bench` does.
+pub fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let measure = args.iter().any(|arg| arg == "--bench");
+    let filter = args.iter().find(|arg| !arg.starts_with('-'));
+
+    let mut benchmarks = inventory::iter::<Benchmark>()
+        .map(|benchmark| (benchmark.path.join(" / "), benchmark))
+        .filter(|(name, _)| filter.map_or(true, |filter| name.contains(filter.as_str())))
+        .collect::<Vec<_>>();
+    benchmarks.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    println!("\nrunning {} benchmarks", benchmarks.len());
+
+    let mut failed = 0;
+    for (name, benchmark) in benchmarks {
+        let mut bencher = if measure {
+            Bencher::measure()
+        } else {
+            Bencher::once()
+        };
+
+        match panic::catch_unwind(AssertUnwindSafe(|| (benchmark.run)(&mut bencher))) {
+            Ok(()) => match bencher.summary {
+                Some(summary) => println!("bench {} ... {}", name, summary),
+                None => println!("bench {} ... ok", name),
+            },
+            Err(payload) => match skipped_reason(&*payload) {
+                Some(reason) => println!("bench {} ... skipped: {}", name, reason),
+                None => {
+                    println!("bench {} ... FAILED", name);
+                    failed += 1;
+                }
+            },
+        }
+    }
+
+    if failed > 0 {
+        println!("\n{} benchmarks failed", failed);
+        process::exit(101);
+    }
+}
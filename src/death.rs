@@ -0,0 +1,162 @@
+//! Runs the death tests declared with `exits_with`, `aborts` and `stderr_contains`, which end the
+//! process that they're run in, in child processes of the test binary
+
+use crate::runner::{
+    child_command, payload_message, report_result, split_result, test_path, Report, Status,
+    TestResult,
+};
+use crate::skip::{skip, skipped_reason};
+use std::env;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::process::{self, ExitStatus};
+
+/// The environment variable that tells a child process to run the code sequences of its death
+/// test rather than spawning another
+const DEATH_TEST: &str = "__DEMONSTRATE_DEATH_TEST";
+
+/// How a death test is expected to end its process
+pub struct Death {
+    pub exit: Exit,
+    /// The strings that the process' stderr is expected to contain
+    pub stderr: &'static [&'static str],
+}
+
+/// The way that a death test's process is expected to exit
+pub enum Exit {
+    /// Exiting with the status code, as with `std::process::exit`
+    Code(i32),
+    /// Aborting, as with `std::process::abort`
+    Abort,
+    /// Exiting in any way
+    Any,
+}
+
+impl Exit {
+    fn matches(&self, status: ExitStatus) -> bool {
+        match self {
+            Exit::Code(code) => status.code() == Some(*code),
+            Exit::Abort => aborted(status),
+            Exit::Any => true,
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exit::Code(code) => write!(f, "exit with status {}", code),
+            Exit::Abort => write!(f, "abort"),
+            Exit::Any => write!(f, "end its process"),
+        }
+    }
+}
+
+/// Whether a process was ended by `std::process::abort`, which raises `SIGABRT`
+#[cfg(unix)]
+fn aborted(status: ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(6)
+}
+
+/// Whether a process was ended by `std::process::abort`, which fails fast with
+/// `STATUS_STACK_BUFFER_OVERRUN`
+#[cfg(windows)]
+fn aborted(status: ExitStatus) -> bool {
+    status.code() == Some(0xC000_0409_u32 as i32)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn aborted(_status: ExitStatus) -> bool {
+    false
+}
+
+/// Runs a death test's code sequences in a child process of the test binary, failing unless that
+/// process ends the way that the test expects
+///
+/// The child process is filtered to the test by the libtest harness or `demonstrate::main!()`,
+/// and reports the test's result like an isolated test does if its code sequences return or
+/// panic rather than ending the process.
+pub fn death_test<R: Report>(
+    module_path: &str,
+    name: &str,
+    expected: Death,
+    test: impl FnOnce() -> R,
+) {
+    if env::var_os(DEATH_TEST).is_some() {
+        run_child(test);
+    }
+
+    // The libtest harness doesn't capture the output of the test it's filtered to, which would be
+    // lost when the process ends
+    let test_path = test_path(module_path, name);
+    let output = child_command(&test_path)
+        .and_then(|mut command| {
+            command
+                .env(DEATH_TEST, "1")
+                .args([&*test_path, "--exact", "--include-ignored", "--nocapture"])
+                .output()
+        })
+        .unwrap_or_else(|error| panic!("Failed spawning a process for the test: {}", error));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let (stderr, result) = split_result(&stderr);
+
+    let unexpected = match result {
+        None if expected.exit.matches(output.status) => None,
+        None => Some(format!("its process ended with {}", output.status)),
+        Some(result) => match result.status {
+            Status::Skipped => skip(result.message.unwrap_or_default()),
+            Status::Passed => Some("it returned".to_owned()),
+            _ => Some(format!(
+                "it failed with:\n{}",
+                result.message.unwrap_or_default()
+            )),
+        },
+    };
+    if let Some(unexpected) = unexpected {
+        panic!(
+            "expected the test to {}, but {}{}",
+            expected.exit,
+            unexpected,
+            stderr_section(stderr)
+        );
+    }
+
+    for expected in expected.stderr {
+        if !stderr.contains(expected) {
+            panic!(
+                "expected the stderr of the test to contain {:?}, but it didn't{}",
+                expected,
+                stderr_section(stderr)
+            );
+        }
+    }
+}
+
+/// Runs a death test's code sequences in the child process spawned for it, reporting their
+/// result and exiting if they didn't end the process themselves
+fn run_child<R: Report>(test: impl FnOnce() -> R) -> ! {
+    let result = match panic::catch_unwind(AssertUnwindSafe(|| test().report())) {
+        Ok(Ok(())) => TestResult::new(Status::Passed, None),
+        Ok(Err(message)) => TestResult::new(Status::Failed, Some(message)),
+        Err(payload) => match skipped_reason(&*payload) {
+            Some(reason) => TestResult::new(Status::Skipped, Some(reason.to_owned())),
+            None => TestResult::new(Status::Failed, Some(payload_message(&*payload))),
+        },
+    };
+    report_result(&result);
+    process::exit(if result.status == Status::Failed {
+        101
+    } else {
+        0
+    });
+}
+
+/// The stderr of a death test's process, to be appended to its failure message
+fn stderr_section(stderr: &str) -> String {
+    if stderr.trim().is_empty() {
+        String::new()
+    } else {
+        format!("\n---- stderr ----\n{}", stderr.trim_end())
+    }
+}
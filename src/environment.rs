@@ -0,0 +1,97 @@
+//! Process-wide state set by the `with_env`, `in_dir` and `in_tempdir` modifiers, which is
+//! restored once a test has finished, even if it panicked
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Restores the environment variables set for a test when it is dropped
+pub struct Env {
+    /// The variables that were set, along with their previous values
+    previous: Vec<(&'static str, Option<OsString>)>,
+}
+
+impl Env {
+    /// Sets the given environment variables in order, so that later values take precedence
+    pub fn set(variables: Vec<(&'static str, OsString)>) -> Self {
+        let mut previous = Vec::with_capacity(variables.len());
+        for (variable, value) in variables {
+            previous.push((variable, env::var_os(variable)));
+            env::set_var(variable, value);
+        }
+
+        Env { previous }
+    }
+}
+
+impl Drop for Env {
+    fn drop(&mut self) {
+        // Variables set more than once are restored to their value from before the first time
+        for (variable, value) in self.previous.drain(..).rev() {
+            match value {
+                Some(value) => env::set_var(variable, value),
+                None => env::remove_var(variable),
+            }
+        }
+    }
+}
+
+/// Restores the working directory changed into for a test when it is dropped, removing the
+/// directory if it was created for the test
+pub struct CurrentDir {
+    previous: PathBuf,
+    current: PathBuf,
+    temporary: bool,
+}
+
+/// The number of temporary directories created by this process, used to name the next one
+static TEMPORARY_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+impl CurrentDir {
+    /// Changes into the given directory, which is relative to the current one
+    pub fn change(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let previous = env::current_dir().expect("Failed getting the working directory");
+        env::set_current_dir(path)
+            .unwrap_or_else(|error| panic!("Failed changing into `{}`: {}", path.display(), error));
+
+        CurrentDir {
+            previous,
+            current: env::current_dir().expect("Failed getting the working directory"),
+            temporary: false,
+        }
+    }
+
+    /// Creates an empty directory within the system's temporary directory and changes into it
+    pub fn temporary() -> Self {
+        let path = env::temp_dir().join(format!(
+            "demonstrate-{}-{}",
+            process::id(),
+            TEMPORARY_DIRS.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&path)
+            .unwrap_or_else(|error| panic!("Failed creating `{}`: {}", path.display(), error));
+
+        let mut current_dir = CurrentDir::change(path);
+        current_dir.temporary = true;
+        current_dir
+    }
+
+    /// The absolute path of the directory that was changed into
+    pub fn path(&self) -> &Path {
+        &self.current
+    }
+}
+
+impl Drop for CurrentDir {
+    fn drop(&mut self) {
+        // Errors can't be reported without panicking again while the test may be panicking
+        let _ = env::set_current_dir(&self.previous);
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.current);
+        }
+    }
+}
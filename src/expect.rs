@@ -0,0 +1,57 @@
+//! Checks the outcomes that tests declared with `panics_with` and `errs_with` expect, once their
+//! `after` code sequences have run
+
+use crate::runner::payload_message;
+use crate::skip::skipped_reason;
+use std::fmt::Debug;
+use std::future::{poll_fn, Future};
+use std::panic::{self, AssertUnwindSafe};
+use std::task::Poll;
+use std::thread;
+
+/// Runs the code sequence of a test, catching its panic
+pub fn catch_unwind<T>(test: impl FnOnce() -> T) -> thread::Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(test))
+}
+
+/// Runs the code sequence of an `async` test, catching a panic from any of its polls
+pub async fn catch_unwind_async<T, F: Future<Output = T>>(future: F) -> thread::Result<T> {
+    let mut future = Box::pin(future);
+    poll_fn(|context| {
+        match panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(context))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    })
+    .await
+}
+
+/// Fails the test unless it panicked with a message containing the expected string
+pub fn expect_panic<T: Debug>(outcome: thread::Result<T>, expected: &str) {
+    match outcome {
+        Ok(value) => panic!(
+            "expected a panic containing {:?}, but the test returned {:?}",
+            expected, value
+        ),
+        // A test that was skipped didn't fail to panic
+        Err(payload) if skipped_reason(&*payload).is_some() => panic::resume_unwind(payload),
+        Err(payload) => {
+            let message = payload_message(&*payload);
+            if !message.contains(expected) {
+                panic!(
+                    "expected a panic containing {:?}, but the test panicked with {:?}",
+                    expected, message
+                );
+            }
+        }
+    }
+}
+
+/// Fails a test that returned a value other than an error matching the given pattern
+pub fn unexpected_result<T: Debug, E: Debug>(result: Result<T, E>, pattern: &str) -> ! {
+    panic!(
+        "expected an error matching `{}`, but the test returned {:?}",
+        pattern, result
+    )
+}
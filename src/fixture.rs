@@ -0,0 +1,46 @@
+//! Fixtures declared with `fixture` blocks, which are set up for each test requesting them
+
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+/// A test-scoped fixture, which is torn down when dropped at the end of the test
+///
+/// Fixtures are dropped in the reverse order that they were set up in, so each is torn down
+/// before the fixtures it depends on.
+pub struct Fixture<T> {
+    value: T,
+    teardown: fn(&mut T),
+}
+
+impl<T> Fixture<T> {
+    pub fn new(value: T, teardown: fn(&mut T)) -> Self {
+        Fixture { value, teardown }
+    }
+}
+
+impl<T> Deref for Fixture<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Fixture<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Drop for Fixture<T> {
+    fn drop(&mut self) {
+        let teardown = self.teardown;
+        if thread::panicking() {
+            // The test has already failed, and a second panic would abort the process
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| teardown(&mut self.value)));
+        } else {
+            teardown(&mut self.value);
+        }
+    }
+}
@@ -146,6 +146,42 @@
 //!     }
 //! }
 //! ```
+//! That's the code that each test runs, but the sync tests of a block that return the same type
+//! may call one function within its module instead, which runs the `before` and `after` code
+//! sequences around the code of the test it's given so that they're only compiled once. Since
+//! their bindings then have one type for all of those tests, this is only done when the
+//! bindings that the tests use have their types written out, as in `let count: u32 = 0;`, or are
+//! literals like `"name"` and `4u8` whose types don't depend on how they're used.
+//!
+//! `let` statements of inherited `before` blocks are left out of the tests that don't use their
+//! bindings, directly or through other statements that are kept, as long as they have no side
+//! effects. Their values can only be made of literals, references, closures, tuples and arrays,
+//! along with `vec!` and `format!`. Other statements, such as those calling functions or
+//! constructing structs that could be guards, can be marked with `#[lazy]` to be left out as well
+//! when unused. Bindings starting with `_`, like `let _guard = lock();`, are always kept.
+//! ```
+//! # use demonstrate::demonstrate;
+//! # fn connect() -> u8 { 4 }
+//! demonstrate! {
+//!     describe "tests" {
+//!         use super::*;
+//!
+//!         before {
+//!             #[lazy]
+//!             let connection = connect();
+//!             let expected = 4;
+//!         }
+//!
+//!         it "connects" {
+//!             assert_eq!(connection, expected)
+//!         }
+//!
+//!         it "runs without connecting" {
+//!             assert!(std::env::args().count() > 0)
+//!         }
+//!     }
+//! }
+//! ```
 //!
 //! <hr />
 //!
@@ -195,21 +231,751 @@
 //! **Note:** If a `describe`/`context` block has a return type with an `after` block containing a
 //! success result type being returned, keep in mind that a compile error will occur if a descendant test
 //! has different return type than the one appearing in that `after` block.
+//!
+//! Inherited attributes come before a block's own, and identical attributes are only applied once.
+//! A block's own `#[should_panic]`, `#[ignore]` or test attribute such as `#[tokio::test]`
+//! replaces the one it would inherit, while `#[no_inherit(..)]` leaves out the inherited
+//! attributes with the given paths and `#[no_inherit]` leaves out all of them:
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     #[should_panic]
+//!     describe "panicking" {
+//!         #[should_panic(expected = "empty")]
+//!         it "explains itself" {
+//!             panic!("empty input")
+//!         }
+//!
+//!         #[no_inherit(should_panic)]
+//!         it "returns a result" -> Result<(), String> {
+//!             Ok(())
+//!         }
+//!     }
+//! }
+//! ```
+//! Attributes that contradict each other, such as `#[should_panic]` on a test returning a
+//! `Result`, are reported as compile errors.
+//!
+//! A test can instead declare the outcome it expects after its name and return type, with
+//! `panics_with ".."` expecting a panic whose message contains the given string and
+//! `errs_with ..` expecting a returned error that matches the given pattern. Unlike
+//! `#[should_panic]`, these are checked after the `after` blocks have run, work for `async` tests,
+//! and report the panic message or value that was returned instead:
+//! ```
+//! # use demonstrate::demonstrate;
+//! # #[derive(Debug)]
+//! # enum ParseError { Eof }
+//! # fn parse(input: &str) -> Result<u8, ParseError> { Err(ParseError::Eof) }
+//! demonstrate! {
+//!     describe "parser" {
+//!         use super::*;
+//!
+//!         it "rejects empty input" panics_with "empty" {
+//!             assert!(!"".is_empty(), "empty input")
+//!         }
+//!
+//!         it "fails to parse" -> Result<u8, ParseError> errs_with ParseError::Eof {
+//!             parse("4")
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Code that ends its process, such as by calling `std::process::exit`, is tested by declaring
+//! `exits_with N` or `aborts` along with any number of `stderr_contains ".."` expectations. Such
+//! a death test runs its code sequences, including the `before` and `after` blocks, in a child
+//! process of the test binary that is filtered to the test, and fails unless that process ends
+//! the way that it expects:
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     describe "config" {
+//!         it "exits on bad config" exits_with 2 stderr_contains "invalid config" {
+//!             eprintln!("error: invalid config");
+//!             std::process::exit(2)
+//!         }
+//!
+//!         it "aborts on corruption" aborts {
+//!             std::process::abort()
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Doc comments on `describe`/`context` blocks document the generated module instead, as do inner
+//! attributes within them and the attributes listed by `#[module(..)]`, none of which are
+//! inherited by the tests:
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     /// Only built on Unix
+//!     #[module(cfg(unix))]
+//!     describe "unix" {
+//!         #![allow(unused_variables)]
+//!
+//!         it "has a root" {
+//!             let root = std::path::Path::new("/");
+//!         }
+//!     }
+//! }
+//! ```
+//! This is generated into:
+//! ```
+//! /// Only built on Unix
+//! #[cfg(unix)]
+//! #[allow(unused_variables)]
+//! mod unix {
+//!     #[test]
+//!     fn has_a_root() {
+//!         let root = std::path::Path::new("/");
+//!     }
+//! }
+//! ```
+//!
+//! <hr />
+//!
+//! `async` tests need a runtime to be executed, which can be selected with `async(tokio)`,
+//! `async(async_std)` or `async(block_on)` and is inherited by descendant blocks like the
+//! `async` token itself. The `tokio` runtime also accepts the `flavor = "multi_thread"` and
+//! `worker_threads = ..` arguments, while `block_on` is the dependency-free executor provided by
+//! [`block_on`](fn.block_on.html).
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     async(block_on) describe "asynchronous" {
+//!         before {
+//!             let ready = async { 4 };
+//!         }
+//!
+//!         it "awaits" {
+//!             assert_eq!(ready.await, 4)
+//!         }
+//!     }
+//! }
+//! ```
+//! This is generated into:
+//! ```
+//! #[cfg(test)]
+//! mod asynchronous {
+//!     #[test]
+//!     fn awaits() {
+//!         demonstrate::block_on(async {
+//!             let ready = async { 4 };
+//!             assert_eq!(ready.await, 4)
+//!         })
+//!     }
+//! }
+//! ```
+//! **Note:** An `async` test without a selected runtime is only generated when it has a test
+//! attribute such as `#[tokio::test]` to execute it, otherwise a compile error will occur. The
+//! runner of [`main!()`](macro.main.html) executes the tests of `#[tokio::test]`,
+//! `#[async_std::test]` and `#[async_attributes::test]` on the same runtime, while it fails those
+//! of other test attributes, which only the libtest harness can call.
+//!
+//! <hr />
+//!
+//! `before_all` and `after_all` blocks are executed once for all the tests within the
+//! `describe`/`context` block they are contained in, including nested blocks. The `let` bindings
+//! of a `before_all` block that have a type annotation are shared with each of those tests and
+//! the `after_all` block as references, so their types must be `Send` and `Sync`.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     async(block_on) describe "shared" {
+//!         before_all {
+//!             let numbers: Vec<u8> = async { vec![1, 2, 3] }.await;
+//!         }
+//!
+//!         after_all {
+//!             assert_eq!(numbers.len(), 3)
+//!         }
+//!
+//!         it "sums" {
+//!             assert_eq!(numbers.iter().sum::<u8>(), 6)
+//!         }
+//!
+//!         it "multiplies" {
+//!             assert_eq!(numbers.iter().product::<u8>(), 6)
+//!         }
+//!     }
+//! }
+//! ```
+//! Both blocks are executed on their own threads with an executor for the block's runtime which,
+//! for Tokio, is kept alive until the `after_all` block has been executed. This allows resources
+//! such as servers and connection pools to be shared across the tests, even though each test
+//! creates its own runtime.
+//!
+//! **Note:** The `after_all` block is executed once every test of the block that is not marked
+//! with `#[ignore]` has finished, so it is skipped when only some of those tests are run.
+//!
+//! <hr />
+//!
+//! Rather than sharing `let` bindings, which are pasted into each test, `before` and `after`
+//! blocks can share a typed context with the tests. Each `state` block declares fields of the
+//! context along with the expressions initializing them, which are merged with the fields
+//! declared by ancestors into a struct that is created for each test and bound to `ctx` before
+//! the `before` code sequences. Declaring a field that an ancestor has already declared is a
+//! compile error, so fields can't shadow each other.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     describe "counter" {
+//!         state {
+//!             count: u32 = 0,
+//!         }
+//!
+//!         before {
+//!             ctx.count += 1;
+//!         }
+//!
+//!         context "with a log" {
+//!             state {
+//!                 log: Vec<String> = vec![],
+//!             }
+//!
+//!             before {
+//!                 ctx.log.push(format!("counted {}", ctx.count));
+//!             }
+//!
+//!             it "logs the count" {
+//!                 assert_eq!(ctx.log, ["counted 1"]);
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! <hr />
+//!
+//! Tests that depend on resources which may not be available can be skipped at runtime with the
+//! [`assume!`](macro.assume.html) macro, or by declaring the environment variables they require
+//! with `skip_unless env ".."` after the description of a block, which applies to all of its
+//! tests.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     describe "database" skip_unless env "DATABASE_URL" {
+//!         use demonstrate::assume;
+//!
+//!         it "connects" {
+//!             assume!(std::path::Path::new("/var/run/postgresql").exists(), "no local server");
+//!         }
+//!     }
+//! }
+//! ```
+//! Skipped tests are reported along with the reason by the runner of
+//! [`main!()`](macro.main.html), while the libtest harness reports them as passing after printing
+//! the reason they were skipped. The environment variables are checked before the state of
+//! `before_all` blocks is set up for a test, which still counts as having finished with it.
+//!
+//! <hr />
+//!
+//! Tests of known bugs can be marked with `xfail ".."` after the description of a block, giving
+//! the reason that its tests are expected to fail. A test that panics or returns an error is then
+//! reported as an expected failure, while a test that passes fails the run so that the bug being
+//! fixed is noticed.
+//! ```
+//! # use demonstrate::demonstrate;
+//! # fn parse(input: &str) -> Result<i32, std::num::ParseIntError> { input.parse() }
+//! demonstrate! {
+//!     describe "parsing" {
+//!         use super::*;
+//!
+//!         it "accepts underscores" -> Result<(), std::num::ParseIntError> xfail "issue 42" {
+//!             assert_eq!(parse("1_000")?, 1000);
+//!             Ok(())
+//!         }
+//!     }
+//! }
+//! ```
+//! The libtest harness reports tests that failed as expected as passing after printing the
+//! reason they were expected to fail.
+//!
+//! <hr />
+//!
+//! Tests declared with `compile_fail` after their description check that their snippet doesn't
+//! compile, such as to test the guarantees of an API's types, optionally expecting the error code
+//! given as in `compile_fail(E0505)`. They're generated as doctests, which rustdoc compiles on
+//! their own and runs along with the rest of a library's doctests under `cargo test`:
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     describe "borrowing" {
+//!         it "rejects moving a borrowed value" compile_fail(E0505) {
+//!             let value = String::new();
+//!             let borrowed = &value;
+//!             drop(value);
+//!             println!("{}", borrowed);
+//!         }
+//!     }
+//! }
+//! ```
+//! **Note:** Rustdoc only collects doctests from libraries, so `compile_fail` tests are rejected
+//! in binaries, examples, integration tests and benchmarks. It compiles libraries with
+//! `#[cfg(doctest)]` rather than `#[cfg(test)]`, so the `demonstrate!` invocation must not be
+//! within a `#[cfg(test)]` module either, where its `compile_fail` tests would silently never
+//! run. The snippet doesn't see the crate's `use` declarations, and its error code is only
+//! checked on nightly.
+//!
+//! <hr />
+//!
+//! Tests that use process-wide state, such as environment variables or a port, can be kept from
+//! running concurrently by marking them or an ancestoral block with `#[serial]`. Each test then
+//! holds a process-wide lock from before its `before` code sequences until after its `after`
+//! code sequences, which tests that panicked while holding it don't prevent others from
+//! acquiring. Independent groups of tests can use separate locks by giving them keys, as in
+//! `#[serial(port)]`, while `#[serial]` alone uses a single lock shared by all tests marked
+//! without a key.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     #[serial(environment)]
+//!     describe "environment" {
+//!         it "sets a variable" {
+//!             std::env::set_var("LOG_LEVEL", "debug");
+//!             assert_eq!(std::env::var("LOG_LEVEL").unwrap(), "debug");
+//!             std::env::remove_var("LOG_LEVEL");
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Blocks can also set the process state for their tests with `with_env { ".." => .. }`, which
+//! sets environment variables to values of any type that converts into an `OsString`, and with
+//! `in_dir(..)` or `in_tempdir`, which change the working directory, the latter into an empty
+//! directory that is bound to `tempdir` and removed once the test has finished. The state is set
+//! after the `before` code sequences and restored once the test has finished, even if it
+//! panicked, with a test's modifiers applied after those of its ancestors. Tests that set
+//! environment variables hold the `#[serial(env)]` lock, while those that change the working
+//! directory hold the `#[serial(cwd)]` lock, so other tests relying on that state can be kept
+//! from running alongside them with those keys.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     describe "configuration" with_env { "APP_ENV" => "test" } {
+//!         it "reads the environment" {
+//!             assert_eq!(std::env::var("APP_ENV").unwrap(), "test");
+//!         }
+//!
+//!         it "writes a file" in_tempdir with_env { "HOME" => tempdir } {
+//!             std::fs::write("config.toml", "").unwrap();
+//!             assert!(tempdir.join("config.toml").exists());
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! <hr />
+//!
+//! `resource` blocks declare a value of the given type, such as a database or a server, that is
+//! set up when the first test using it leases it and torn down by its optional `teardown` block
+//! once the last of them has finished. Tests use a resource by marking themselves or an ancestoral
+//! block with `#[resource(..)]` and the resource's path, which binds its name in their `before`
+//! blocks and code sequences, so that tests in any module can share it. Both blocks are executed
+//! on their own threads, with `teardown` seeing the instance through a reference.
+//!
+//! The runner of [`main!()`](macro.main.html) tears a resource down after the last of the tests
+//! that it runs using it. Otherwise, a resource is torn down whenever no test is using it, and set
+//! up again for the next one.
+//!
+//! A single instance is shared by every test, while `resource(pool = N)` sets up to `N`
+//! instances, each of which is leased mutably by one test at a time, and tests wait for an
+//! instance to be returned when they're all in use.
+//! ```
+//! # use demonstrate::demonstrate;
+//! # struct Database;
+//! # impl Database {
+//! #     fn open() -> Self { Database }
+//! #     fn query(&self, query: &str) -> usize { 4 }
+//! #     fn close(&self) {}
+//! # }
+//! demonstrate! {
+//!     describe "storage" {
+//!         use super::*;
+//!
+//!         resource "database" -> Database {
+//!             Database::open()
+//!         } teardown {
+//!             database.close();
+//!         }
+//!
+//!         resource(pool = 4) "scratch" -> Vec<u8> {
+//!             Vec::with_capacity(1024)
+//!         }
+//!
+//!         #[resource(database, scratch)]
+//!         it "queries" {
+//!             scratch.clear();
+//!             assert_eq!(database.query("SELECT 4"), 4);
+//!         }
+//!     }
+//!
+//!     describe "reporting" {
+//!         #[resource(super::storage::database)]
+//!         it "shares the database" {
+//!             assert_eq!(database.query("SELECT 4"), 4);
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! <hr />
+//!
+//! `fixture` blocks declare a value of the given type that tests within the block can request by
+//! name, binding it as in `it ".." (db: temp_db)`, while blocks can request fixtures for all of
+//! their tests. Fixtures can likewise request other fixtures declared by the same block or an
+//! ancestor, which are set up before them, and cycles are rejected at compile time. Each test
+//! sets up the fixtures it requests, along with their dependencies, after its `before` code
+//! sequences, and tears them down in the reverse order once it has finished, whether by dropping
+//! them or with their optional `teardown` blocks, which see their fixture through a mutable
+//! reference. Tests can mutate the fixtures they request, while fixtures only see their
+//! dependencies through shared references. A fixture declared by a nested block takes precedence
+//! over an ancestor's with the same name.
+//!
+//! Fixtures declared with `fixture(describe)` are instead set up once for the tests of the block
+//! declaring them that use them, like the state of a `before_all` block, and can only depend on
+//! other such fixtures. Tests see them through shared references.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     describe "storage" {
+//!         use std::path::PathBuf;
+//!
+//!         fixture(describe) root -> PathBuf {
+//!             std::env::temp_dir()
+//!         }
+//!
+//!         fixture file(root: root) -> PathBuf {
+//!             let file = root.join(format!("demonstrate-{}", std::process::id()));
+//!             std::fs::write(&file, "contents").unwrap();
+//!             file
+//!         } teardown {
+//!             std::fs::remove_file(file).unwrap();
+//!         }
+//!
+//!         it "reads a file" (file: file) {
+//!             assert_eq!(std::fs::read_to_string(file).unwrap(), "contents");
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! <hr />
+//!
+//! `bench` blocks define a benchmark of their code sequence, with the `before` and `after` blocks
+//! of ancestoral `describe`/`context` blocks executed outside of the timed region.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     describe "parsing" {
+//!         before {
+//!             let input = vec!["4"; 1024].join(",");
+//!         }
+//!
+//!         bench "sums 1KB of numbers" {
+//!             input.split(',').map(|n| n.parse::<u32>().unwrap()).sum::<u32>()
+//!         }
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+//! This is generated into:
+//! ```ignore
+//! mod parsing {
+//!     fn sums_1kb_of_numbers(__bencher: &mut demonstrate::Bencher) {
+//!         let input = vec!["4"; 1024].join(",");
+//!         __bencher.iter(|| {
+//!             input.split(',').map(|n| n.parse::<u32>().unwrap()).sum::<u32>()
+//!         });
+//!     }
+//!
+//!     // Registers the benchmark with `demonstrate::bench_main!()`
+//! }
+//! ```
+//! These benchmarks are run on stable Rust by a benchmark target with `harness = false` that calls
+//! [`bench_main!()`](macro.bench_main.html), which warms each benchmark up before timing a number
+//! of samples and reporting the mean, median and standard deviation of their durations. As with
+//! the libtest harness, each benchmark is only executed once unless the `--bench` flag is passed,
+//! as `cargo bench` does.
+//! ```toml
+//! [[bench]]
+//! name = "spec"
+//! harness = false
+//! ```
+//! Alternatively, `bench(nightly)` blocks generate `#[bench]` functions for the libtest harness,
+//! which require `#![feature(test)]` and `extern crate test;` in the crate root.
+//!
+//! <hr />
+//!
+//! The specification tree of each root `describe`/`context` block can be exported as Markdown
+//! by setting the `DEMONSTRATE_DOCS_DIR` environment variable to a directory, relative to the
+//! crate's manifest directory, when compiling the tests. Doc comments on blocks are included
+//! beneath their descriptions.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     /// Futures are awaited
+//!     describe "asynchronous" {
+//!         it "awaits" {}
+//!
+//!         context "when ready" {
+//!             it "returns immediately" {}
+//!         }
+//!     }
+//! }
+//! ```
+//! This is exported to `my_crate.src_lib.asynchronous.md`, named after the crate and source file
+//! declaring the block so that blocks with the same name in other test targets or files are
+//! exported separately, as:
+//! ```markdown
+//! # asynchronous
+//!
+//! Futures are awaited
+//!
+//! - awaits
+//!
+//! ## when ready
+//!
+//! - returns immediately
+//! ```
+//! The variable can be set for every build in the `[env]` table of `.cargo/config.toml`:
+//! ```toml
+//! [env]
+//! DEMONSTRATE_DOCS_DIR = "target/spec"
+//! ```
+//!
+//! Similarly, setting the `DEMONSTRATE_MANIFEST_DIR` environment variable exports a JSON manifest
+//! of each root `describe`/`context` block, which maps every generated test and benchmark to its
+//! description, source location, tags, and whether it is `async` or ignored, along with its
+//! return type. Tags are declared with `#[tag(..)]` and are inherited by descendant blocks.
+//! ```
+//! # use demonstrate::demonstrate;
+//! demonstrate! {
+//!     #[tag("io")]
+//!     describe "files" {
+//!         #[tag("slow")]
+//!         it "reads" {}
+//!     }
+//! }
+//! ```
+//! This is exported to `my_crate.src_lib.files.json`, named like the Markdown files, as:
+//! ```json
+//! {
+//!   "source_file": "src/lib.rs",
+//!   "tests": [
+//!     {"kind": "test", "description": ["files", "reads"], "test_path": "files::reads", "line": 7, "column": 12, "tags": ["io", "slow"], "async": false, "ignored": false, "return_type": null}
+//!   ]
+//! }
+//! ```
+//! **Note:** The `test_path` is relative to the module that the `demonstrate!` macro was called
+//! in, which is the full test path within an integration test's crate root.
+//!
+//! <hr />
+//!
+//! Instead of libtest's flat list of module paths, the tests of an integration test target with
+//! `harness = false` can be reported as a tree of their descriptions by calling
+//! [`main!()`](macro.main.html) within it.
+//! ```toml
+//! [[test]]
+//! name = "spec"
+//! harness = false
+//! ```
+//! ```ignore
+//! use demonstrate::demonstrate;
+//!
+//! demonstrate! {
+//!     describe "asynchronous" {
+//!         it "awaits" {}
+//!
+//!         #[ignore]
+//!         it "times out" {}
+//!     }
+//! }
+//!
+//! demonstrate::main!();
+//! ```
+//! Running `cargo test --test spec` then prints:
+//! ```text
+//! asynchronous
+//!   ✓ awaits
+//!   - times out (IGNORED)
+//!
+//! Finished in 0.00 seconds
+//! 2 tests, 0 failures, 1 ignored
+//! ```
+//! The runner accepts the common libtest arguments, such as test name filters, `--skip`,
+//! `--exact`, `--ignored`, `--include-ignored`, `--list` and `--test-threads`.
+//!
+//! The results can instead be reported as JUnit XML, TAP or JSON lines by passing `--format junit`,
+//! `--format tap` or `--format json`, or by setting the `DEMONSTRATE_FORMAT` environment variable.
+//! These include the duration of each test and its failure message, and group the tests by the
+//! descriptions of their ancestoral blocks. Other formats can be added by implementing the
+//! [`Reporter`](trait.Reporter.html) trait.
+//! ```text
+//! cargo test --test spec -- --format junit --report-file target/spec.xml
+//! ```
+//! The report is written to stdout unless a file is given with `--report-file` or the
+//! `DEMONSTRATE_REPORT_FILE` environment variable, which keeps it apart from anything the tests
+//! print.
+//! As stable Rust can't capture what a test prints within the same process, each test is run in
+//! its own process when the `--isolate` flag is passed or the `DEMONSTRATE_ISOLATE` environment
+//! variable is set, so that its output is reported along with its result.
+//!
+//! Tests that depend on the order they're run in can be found by shuffling them with the
+//! `--shuffle` flag or the `DEMONSTRATE_SHUFFLE` environment variable. Sibling blocks are shuffled
+//! while the tests of each `describe`/`context` block are kept together, and the seed of the order
+//! is printed so that it can be reproduced with `--shuffle-seed <seed>` or the
+//! `DEMONSTRATE_SHUFFLE_SEED` environment variable. Passing `--test-threads 1` as well ensures
+//! that the tests are also executed in exactly that order.
+//!
+//! The description paths of the tests that fail are persisted to
+//! `target/demonstrate/<target name>.failures`, or to the file named by the
+//! `DEMONSTRATE_FAILURES_FILE` environment variable. Passing `--only-failures` then runs just
+//! those tests, while `--next-failure` also stops the run at the first of them that still fails.
+//!
+//! Large suites can be split across several machines by giving each of them a shard to run, such
+//! as `--shard 2/5` or `DEMONSTRATE_SHARD=2/5`. By default, the tests of each `describe`/`context`
+//! block are kept within the same shard where possible while balancing the number of tests in
+//! each. Passing `--shard-by durations` or setting `DEMONSTRATE_SHARD_BY=durations` instead
+//! balances the tests by how long they took when last run, as persisted to
+//! `target/demonstrate/<target name>.durations` or the file named by the
+//! `DEMONSTRATE_DURATIONS_FILE` environment variable. Every shard must be given the same
+//! durations for the tests to be partitioned the same way on each machine. Shards may be given up
+//! to a quarter more than an even share, so that adding or removing tests moves few of the others
+//! to another shard.
+//!
+//! The runner times every test along with each of the `before` and `after` blocks executed as
+//! part of it. Passing `--profile` or setting `DEMONSTRATE_PROFILE` prints the 10 slowest tests
+//! and the `describe`/`context` blocks whose `before` and `after` blocks took the most time in
+//! total once the run has finished, while `--profile=<count>` or `DEMONSTRATE_PROFILE=<count>`
+//! changes how many are printed.
 
-extern crate proc_macro;
+#![allow(clippy::test_attr_in_doctest)]
 
-use crate::block::Root;
-use crate::generate::Generate;
+pub use bench::Bencher;
+pub use demonstrate_macros::demonstrate;
+pub use runner::{Hook, HookTiming, Reporter, ReporterFactory, Status, TestCase, TestResult};
+pub use runtime::block_on;
 
-mod block;
-mod generate;
-mod inherit;
+mod bench;
+mod death;
+mod environment;
+mod expect;
+mod fixture;
+mod resource;
+mod runner;
+mod runtime;
+mod serial;
+mod shared;
+mod skip;
+mod xfail;
+
+/// Items used by the code generated from the `demonstrate!` macro
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::bench::{main as bench_main, Benchmark};
+    pub use crate::death::{death_test, Death, Exit};
+    pub use crate::environment::{CurrentDir, Env};
+    pub use crate::expect::{catch_unwind, catch_unwind_async, expect_panic, unexpected_result};
+    pub use crate::fixture::Fixture;
+    pub use crate::resource::{Pool, Resource, Users};
+    pub use crate::runner::{
+        main as run_tests, Hook, HookTimer, HookTimings, Report, ShouldPanic, TestCase,
+    };
+    pub use crate::serial::{serial, Serial};
+    pub use crate::shared::{Lease, Leased, Shared};
+    pub use crate::skip::{libtest, skip, Skippable, Skipped};
+    pub use crate::xfail::libtest_xfail;
+    pub use inventory;
+}
+
+/// Skips the current test unless a condition holds, such as a resource that it depends on being
+/// available
+///
+/// The test is reported as skipped, along with the reason, by the runner of
+/// [`main!()`](macro.main.html). The libtest harness reports it as passing, but the reason it was
+/// skipped is still printed.
+/// ```
+/// # use demonstrate::{assume, demonstrate};
+/// demonstrate! {
+///     describe "database" {
+///         it "connects" {
+///             assume!(std::env::var_os("DATABASE_URL").is_some(), "DATABASE_URL is not set");
+///         }
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! assume {
+    ($condition:expr $(,)?) => {
+        $crate::assume!($condition, "assumption failed: {}", stringify!($condition))
+    };
+    ($condition:expr, $($reason:tt)+) => {
+        if !$condition {
+            $crate::__private::skip(::std::format!($($reason)+))
+        }
+    };
+}
+
+/// Generates the `main` function of a test target with `harness = false`, which runs the tests
+/// generated by the `demonstrate!` macro and reports them as a tree of their descriptions
+///
+/// Additional [`Reporter`](trait.Reporter.html)s can be selected with the `--format` flag or
+/// `DEMONSTRATE_FORMAT` environment variable after being given along with their format's name,
+/// as in `demonstrate::main!("dots" => Dots::new())`. The benchmarks declared with `bench` blocks
+/// are run instead when the `--bench` flag is passed.
+#[macro_export]
+macro_rules! main {
+    ($($format:expr => $reporter:expr),* $(,)?) => {
+        fn main() {
+            $crate::__private::run_tests(&[$((
+                $format,
+                || ::std::boxed::Box::new($reporter) as ::std::boxed::Box<dyn $crate::Reporter>,
+            )),*])
+        }
+    };
+}
+
+/// Generates the `main` function of a benchmark target with `harness = false`, which runs the
+/// benchmarks declared with `bench` blocks
+#[macro_export]
+macro_rules! bench_main {
+    () => {
+        fn main() {
+            $crate::__private::bench_main()
+        }
+    };
+}
 
-#[proc_macro]
-pub fn demonstrate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = proc_macro2::TokenStream::from(input);
+// The guarantees of the public API that are enforced by the compiler, which rustdoc checks with
+// the generated `compile_fail` doctests
+#[cfg(doctest)]
+demonstrate! {
+    describe "api" {
+        it "keeps test results from being constructed" compile_fail(E0639) {
+            let result = demonstrate::TestResult {
+                status: demonstrate::Status::Passed,
+                duration: std::time::Duration::ZERO,
+                message: None,
+                output: None,
+                hooks: Vec::new(),
+            };
+        }
 
-    let mut root = syn::parse2::<Root>(input).unwrap();
+        it "keeps statuses from being matched exhaustively" compile_fail(E0004) {
+            fn failed(status: demonstrate::Status) -> bool {
+                match status {
+                    demonstrate::Status::Passed => false,
+                    demonstrate::Status::Failed => true,
+                    demonstrate::Status::Ignored => false,
+                    demonstrate::Status::Skipped => false,
+                    demonstrate::Status::ExpectedFailure => false,
+                }
+            }
+        }
 
-    proc_macro::TokenStream::from(root.generate(None))
+        it "only blocks on futures" compile_fail(E0277) {
+            demonstrate::block_on(4);
+        }
+    }
 }
@@ -0,0 +1,319 @@
+//! Resources declared with `resource` blocks, which are set up when first leased by a test and
+//! torn down once the last test using them has finished
+
+use std::ops::{Deref, DerefMut};
+use std::panic;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// Tracks the tests that use a resource, so that it can be torn down after the last of them
+///
+/// When the tests are run by `demonstrate::main!()`, the runner declares each test that will use
+/// the resource before running any of them, and the resource is torn down once they have all
+/// finished. Otherwise, the resource is torn down whenever no test is using it.
+pub trait Users: Sync {
+    /// The name of the resource
+    fn name(&self) -> &'static str;
+    /// Declares that a test which will be run uses the resource
+    fn expect_user(&self);
+    /// Declares that a test which uses the resource has finished, returning the payload of the
+    /// panic if the resource was torn down and its teardown panicked
+    fn user_finished(&self) -> thread::Result<()>;
+}
+
+/// The users of a resource, along with its instances
+struct State<T> {
+    /// The number of tests that the runner declared will use the resource
+    expected: usize,
+    /// The number of those tests that have finished
+    finished: usize,
+    /// The number of leases that haven't been dropped
+    active: usize,
+    /// The instances that aren't leased
+    idle: Vec<T>,
+    /// The number of instances that are set up, whether idle or leased
+    created: usize,
+}
+
+impl<T> State<T> {
+    const fn new() -> Self {
+        State {
+            expected: 0,
+            finished: 0,
+            active: 0,
+            idle: Vec::new(),
+            created: 0,
+        }
+    }
+
+    /// Whether every user of the resource has finished with it
+    fn is_finished(&self) -> bool {
+        self.active == 0 && self.finished >= self.expected
+    }
+
+    /// Takes the instances for them to be torn down, resetting the counts of the resource's users
+    fn take_instances(&mut self) -> Vec<T> {
+        self.expected = 0;
+        self.finished = 0;
+        self.created = 0;
+        self.idle.drain(..).collect()
+    }
+}
+
+/// The instances of a resource, along with how they're set up and torn down
+struct Instances<T> {
+    name: &'static str,
+    state: Mutex<State<T>>,
+    /// Notified when an instance is returned or torn down
+    returned: Condvar,
+    setup: fn() -> T,
+    teardown: fn(T),
+}
+
+impl<T: Send + 'static> Instances<T> {
+    const fn new(name: &'static str, setup: fn() -> T, teardown: fn(T)) -> Self {
+        Instances {
+            name,
+            state: Mutex::new(State::new()),
+            returned: Condvar::new(),
+            setup,
+            teardown,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Tears the instances down if every user of the resource has finished with it
+    ///
+    /// Like the setup, each teardown is executed on its own thread.
+    fn tear_down_if_finished(&self, mut state: MutexGuard<'_, State<T>>) -> thread::Result<()> {
+        if !state.is_finished() {
+            return Ok(());
+        }
+        let instances = state.take_instances();
+        drop(state);
+
+        let teardown = self.teardown;
+        let results = instances
+            .into_iter()
+            .map(|instance| thread::spawn(move || teardown(instance)).join())
+            .collect::<Vec<_>>();
+        self.returned.notify_all();
+
+        results.into_iter().collect()
+    }
+
+    fn expect_user(&self) {
+        self.lock().expected += 1;
+    }
+
+    fn user_finished(&self) -> thread::Result<()> {
+        let mut state = self.lock();
+        state.finished += 1;
+        self.tear_down_if_finished(state)
+    }
+
+    /// Counts a lease as dropped, tearing the instances down if it was the last one
+    ///
+    /// A panicking teardown is propagated to the test that dropped the lease, unless it is
+    /// already panicking.
+    fn lease_dropped(&self, state: MutexGuard<'_, State<T>>) {
+        if let (Err(payload), false) = (self.tear_down_if_finished(state), thread::panicking()) {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+/// Sets up an instance of a resource on its own thread, so that it's free to start a runtime of
+/// its own even when leased by an async test
+fn set_up<T: Send + 'static>(setup: fn() -> T) -> thread::Result<T> {
+    thread::spawn(setup).join()
+}
+
+/// A resource whose single instance is shared by the tests using it
+pub struct Resource<T> {
+    instances: Instances<Arc<T>>,
+}
+
+impl<T: Send + Sync + 'static> Resource<T> {
+    pub const fn new(name: &'static str, setup: fn() -> Arc<T>, teardown: fn(Arc<T>)) -> Self {
+        Resource {
+            instances: Instances::new(name, setup, teardown),
+        }
+    }
+
+    /// Leases the resource, setting it up if it isn't already
+    pub fn lease(&'static self) -> ResourceLease<T> {
+        let instances = &self.instances;
+        let mut state = instances.lock();
+        state.active += 1;
+
+        let instance = match state.idle.first() {
+            Some(instance) => instance.clone(),
+            None => {
+                // The lock is held while the instance is set up, so that it's only set up once
+                let instance = match set_up(instances.setup) {
+                    Ok(instance) => instance,
+                    Err(payload) => {
+                        state.active -= 1;
+                        drop(state);
+                        panic::resume_unwind(payload)
+                    }
+                };
+                state.idle.push(instance.clone());
+                state.created = 1;
+                instance
+            }
+        };
+
+        ResourceLease {
+            resource: self,
+            instance: Some(instance),
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> Users for Resource<T> {
+    fn name(&self) -> &'static str {
+        self.instances.name
+    }
+
+    fn expect_user(&self) {
+        self.instances.expect_user()
+    }
+
+    fn user_finished(&self) -> thread::Result<()> {
+        self.instances.user_finished()
+    }
+}
+
+/// A test's lease of a shared resource
+pub struct ResourceLease<T: Send + Sync + 'static> {
+    resource: &'static Resource<T>,
+    instance: Option<Arc<T>>,
+}
+
+impl<T: Send + Sync + 'static> Deref for ResourceLease<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.instance.as_ref().unwrap()
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for ResourceLease<T> {
+    fn drop(&mut self) {
+        // The lease's reference is dropped first, so that the instance can be torn down by value
+        drop(self.instance.take());
+
+        let instances = &self.resource.instances;
+        let mut state = instances.lock();
+        state.active -= 1;
+        instances.lease_dropped(state);
+    }
+}
+
+/// A resource with a pool of instances, each of which is leased exclusively by one test at a time
+pub struct Pool<T> {
+    size: usize,
+    instances: Instances<T>,
+}
+
+impl<T: Send + 'static> Pool<T> {
+    pub const fn new(name: &'static str, size: usize, setup: fn() -> T, teardown: fn(T)) -> Self {
+        Pool {
+            size,
+            instances: Instances::new(name, setup, teardown),
+        }
+    }
+
+    /// Leases an idle instance of the resource, setting one up if the pool isn't full or waiting
+    /// for one to be returned if it is
+    pub fn lease(&'static self) -> PoolLease<T> {
+        let instances = &self.instances;
+        let mut state = instances.lock();
+        state.active += 1;
+
+        loop {
+            if let Some(instance) = state.idle.pop() {
+                return PoolLease {
+                    pool: self,
+                    instance: Some(instance),
+                };
+            }
+            if state.created < self.size {
+                // The instance is set up without holding the lock, so that the other instances
+                // can be leased and returned in the meantime
+                state.created += 1;
+                drop(state);
+                match set_up(instances.setup) {
+                    Ok(instance) => {
+                        return PoolLease {
+                            pool: self,
+                            instance: Some(instance),
+                        }
+                    }
+                    Err(payload) => {
+                        let mut state = instances.lock();
+                        state.created -= 1;
+                        state.active -= 1;
+                        drop(state);
+                        instances.returned.notify_all();
+                        panic::resume_unwind(payload)
+                    }
+                }
+            }
+            state = instances
+                .returned
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+impl<T: Send + 'static> Users for Pool<T> {
+    fn name(&self) -> &'static str {
+        self.instances.name
+    }
+
+    fn expect_user(&self) {
+        self.instances.expect_user()
+    }
+
+    fn user_finished(&self) -> thread::Result<()> {
+        self.instances.user_finished()
+    }
+}
+
+/// A test's exclusive lease of one of the instances of a pooled resource
+pub struct PoolLease<T: Send + 'static> {
+    pool: &'static Pool<T>,
+    instance: Option<T>,
+}
+
+impl<T: Send + 'static> Deref for PoolLease<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.instance.as_ref().unwrap()
+    }
+}
+
+impl<T: Send + 'static> DerefMut for PoolLease<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.instance.as_mut().unwrap()
+    }
+}
+
+impl<T: Send + 'static> Drop for PoolLease<T> {
+    fn drop(&mut self) {
+        let instances = &self.pool.instances;
+        let mut state = instances.lock();
+        state.active -= 1;
+        state.idle.extend(self.instance.take());
+        instances.returned.notify_all();
+        instances.lease_dropped(state);
+    }
+}
@@ -0,0 +1,165 @@
+//! Reports tests as a tree of their descriptions, like the documentation formatter of RSpec
+
+use super::{Reporter, Status, TestCase, TestResult};
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+
+/// Prints each test beneath the descriptions of its ancestoral blocks as it finishes, followed
+/// by the messages of the failed tests and a summary
+pub(crate) struct Documentation {
+    /// The descriptions of the ancestoral blocks of the last test that was printed
+    groups: Vec<&'static str>,
+    /// The number of tests that have failed so far
+    failures: usize,
+    color: bool,
+}
+
+impl Documentation {
+    pub(crate) fn new() -> Self {
+        Documentation {
+            groups: Vec::new(),
+            failures: 0,
+            color: io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    /// Colors text with an ANSI escape code if stdout is a terminal
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+impl Reporter for Documentation {
+    fn test_finished(&mut self, test: &'static TestCase, result: &TestResult) {
+        let (name, groups) = test.path.split_last().unwrap();
+
+        // Only print the descriptions of the ancestoral blocks that weren't already printed
+        let common = self
+            .groups
+            .iter()
+            .zip(groups)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, group) in groups.iter().enumerate().skip(common) {
+            if depth == 0 {
+                println!();
+            }
+            println!("{}{}", indent(depth), group);
+        }
+        self.groups = groups.to_vec();
+
+        let indent = indent(groups.len());
+        match result.status {
+            Status::Passed => println!("{}{} {}", indent, self.paint(GREEN, "✓"), name),
+            Status::Failed => {
+                self.failures += 1;
+                let line = format!("✗ {} (FAILED - {})", name, self.failures);
+                println!("{}{}", indent, self.paint(RED, &line));
+            }
+            Status::Ignored => {
+                let line = format!("- {} (IGNORED)", name);
+                println!("{}{}", indent, self.paint(YELLOW, &line));
+            }
+            Status::Skipped => {
+                let reason = result.message.as_deref().unwrap_or_default();
+                let line = format!("- {} (SKIPPED: {})", name, reason);
+                println!("{}{}", indent, self.paint(YELLOW, &line));
+            }
+            Status::ExpectedFailure => {
+                let reason = test.xfail.unwrap_or_default();
+                let line = format!("✗ {} (EXPECTED FAILURE: {})", name, reason);
+                println!("{}{}", indent, self.paint(YELLOW, &line));
+            }
+        }
+    }
+
+    fn run_finished(
+        &mut self,
+        tests: &[&'static TestCase],
+        results: &[TestResult],
+        elapsed: Duration,
+    ) {
+        let failures = tests
+            .iter()
+            .zip(results)
+            .filter(|(_, result)| result.status == Status::Failed)
+            .collect::<Vec<_>>();
+
+        if !failures.is_empty() {
+            println!("\nFailures:");
+            for (number, (test, result)) in failures.iter().enumerate() {
+                println!("\n  {}) {}", number + 1, test.path.join(" "));
+                for line in result.message.as_deref().unwrap_or_default().lines() {
+                    println!("     {}", self.paint(RED, line));
+                }
+                if let Some(output) = result.output.as_deref().filter(|output| !output.is_empty()) {
+                    println!("\n     ---- output ----");
+                    for line in output.lines() {
+                        println!("     {}", line);
+                    }
+                }
+                let location = format!("# {}:{}:{}", test.file, test.line, test.column);
+                println!("     {}", self.paint(CYAN, &location));
+            }
+        }
+
+        let count = |status| {
+            results
+                .iter()
+                .filter(|result| result.status == status)
+                .count()
+        };
+        let ignored = count(Status::Ignored);
+        let skipped = count(Status::Skipped);
+        let expected_failures = count(Status::ExpectedFailure);
+        let mut summary = format!(
+            "{} {}, {} {}",
+            results.len(),
+            plural(results.len(), "test"),
+            failures.len(),
+            plural(failures.len(), "failure"),
+        );
+        if ignored > 0 {
+            summary.push_str(&format!(", {} ignored", ignored));
+        }
+        if skipped > 0 {
+            summary.push_str(&format!(", {} skipped", skipped));
+        }
+        if expected_failures > 0 {
+            summary.push_str(&format!(
+                ", {} expected {}",
+                expected_failures,
+                plural(expected_failures, "failure")
+            ));
+        }
+
+        println!("\nFinished in {:.2} seconds", elapsed.as_secs_f64());
+        let color = match (failures.is_empty(), ignored + skipped + expected_failures) {
+            (false, _) => RED,
+            (true, 0) => GREEN,
+            (true, _) => YELLOW,
+        };
+        println!("{}", self.paint(color, &summary));
+    }
+}
+
+const GREEN: &str = "32";
+const RED: &str = "31";
+const YELLOW: &str = "33";
+const CYAN: &str = "36";
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        noun.to_owned()
+    } else {
+        format!("{}s", noun)
+    }
+}
@@ -0,0 +1,63 @@
+//! Persists how long each test took the last time that it was run, so that shards can be balanced
+//! by duration
+
+use super::failures::{description_path, persisted_file, write};
+use super::{TestCase, TestResult};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The durations of the tests, by their description paths
+pub(crate) struct Durations {
+    /// The file that the durations are persisted in, if it could be determined
+    file: Option<PathBuf>,
+    seconds: BTreeMap<String, f64>,
+}
+
+impl Durations {
+    /// Loads the durations persisted for the current test binary
+    pub(crate) fn load() -> Self {
+        Durations::read(persisted_file("durations", "DEMONSTRATE_DURATIONS_FILE"))
+    }
+
+    /// Reads the durations persisted in a file, which are then updated in it
+    pub(crate) fn read(file: Option<PathBuf>) -> Self {
+        let seconds = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|durations| {
+                durations
+                    .lines()
+                    .filter_map(|line| {
+                        let (seconds, path) = line.split_once('\t')?;
+                        Some((path.to_owned(), seconds.parse().ok()?))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Durations { file, seconds }
+    }
+
+    /// How long the test took the last time that it was run, in seconds
+    pub(crate) fn get(&self, test: &TestCase) -> Option<f64> {
+        self.seconds.get(&description_path(test)).copied()
+    }
+
+    /// Records the durations of the tests that were run
+    pub(crate) fn update(&mut self, tests: &[&TestCase], results: &[TestResult]) -> io::Result<()> {
+        for (test, result) in tests.iter().zip(results) {
+            if result.duration > Duration::ZERO {
+                let seconds = result.duration.as_secs_f64();
+                self.seconds.insert(description_path(test), seconds);
+            }
+        }
+
+        let mut contents = String::new();
+        for (path, seconds) in &self.seconds {
+            contents.push_str(&format!("{:.6}\t{}\n", seconds, path));
+        }
+        write(&self.file, contents)
+    }
+}
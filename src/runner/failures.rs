@@ -0,0 +1,207 @@
+//! Persists the description paths of the tests that failed, so that later runs can run only them
+//!
+//! This also locates the files that other records of the runs are persisted in.
+
+use super::{Status, TestCase, TestResult};
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The description paths of the tests that failed the last time that they were run
+pub(crate) struct Failures {
+    /// The file that the failures are persisted in, if it could be determined
+    file: Option<PathBuf>,
+    paths: BTreeSet<String>,
+}
+
+impl Failures {
+    /// Loads the failures persisted for the current test binary
+    pub(crate) fn load() -> Self {
+        Failures::read(persisted_file("failures", "DEMONSTRATE_FAILURES_FILE"))
+    }
+
+    /// Reads the failures persisted in a file, which are then updated in it
+    fn read(file: Option<PathBuf>) -> Self {
+        let paths = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|failures| failures.lines().map(str::to_owned).collect())
+            .unwrap_or_default();
+        Failures { file, paths }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Whether the test failed the last time that it was run
+    pub(crate) fn contains(&self, test: &TestCase) -> bool {
+        self.paths.contains(&description_path(test))
+    }
+
+    /// Records the results of the tests that were run, keeping the failures of the tests that
+    /// weren't
+    pub(crate) fn update(&mut self, tests: &[&TestCase], results: &[TestResult]) -> io::Result<()> {
+        for (test, result) in tests.iter().zip(results) {
+            let path = description_path(test);
+            match result.status {
+                Status::Failed => {
+                    self.paths.insert(path);
+                }
+                Status::Ignored => {}
+                _ => {
+                    self.paths.remove(&path);
+                }
+            }
+        }
+
+        let mut contents = String::new();
+        for path in &self.paths {
+            contents.push_str(path);
+            contents.push('\n');
+        }
+        write(&self.file, contents)
+    }
+}
+
+/// The descriptions of a test's ancestoral blocks followed by its own
+///
+/// Backslashes, slashes and line breaks are escaped within each description, so that a
+/// description containing the separator can't make the paths of two tests the same.
+pub(crate) fn description_path(test: &TestCase) -> String {
+    test.path
+        .iter()
+        .map(|description| {
+            description
+                .replace('\\', "\\\\")
+                .replace('/', "\\/")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        })
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// The file that a record of the current test binary's runs is persisted in, which is
+/// `target/demonstrate/<target name>.<extension>` unless the environment `variable` is set
+pub(crate) fn persisted_file(extension: &str, variable: &str) -> Option<PathBuf> {
+    if let Some(file) = env::var_os(variable) {
+        return Some(file.into());
+    }
+
+    // Test binaries are built as `target/<profile>/deps/<target name>-<hash>`
+    let exe = env::current_exe().ok()?;
+    let deps = exe.parent().filter(|deps| deps.ends_with("deps"))?;
+    let target = deps.parent()?.parent()?;
+    let name = exe.file_stem()?.to_str()?;
+    let name = name.rsplit_once('-').map_or(name, |(name, _hash)| name);
+    Some(
+        target
+            .join("demonstrate")
+            .join(format!("{}.{}", name, extension)),
+    )
+}
+
+/// Writes a persisted file, if its location could be determined
+pub(crate) fn write(file: &Option<PathBuf>, contents: String) -> io::Result<()> {
+    let Some(file) = file else {
+        return Ok(());
+    };
+    if let Some(directory) = file.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(file, contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::test_case;
+
+    #[test]
+    fn reruns_the_failures_of_the_last_run() {
+        let file = env::temp_dir().join(format!("demonstrate-{}.failures", std::process::id()));
+        let tests = [
+            test_case(&["parser", "parses"]),
+            test_case(&["parser", "rejects"]),
+            test_case(&["server", "starts"]),
+            test_case(&["server", "stops"]),
+        ];
+        let tests = tests.iter().collect::<Vec<_>>();
+        let result = |status| TestResult::new(status, None);
+
+        let mut failures = Failures::read(Some(file.clone()));
+        assert!(failures.is_empty());
+        let results = [
+            Status::Failed,
+            Status::Passed,
+            Status::Failed,
+            Status::Failed,
+        ]
+        .map(result);
+        failures.update(&tests, &results).unwrap();
+
+        // Only the failed tests are rerun
+        let mut failures = Failures::read(Some(file.clone()));
+        let rerun = tests
+            .iter()
+            .copied()
+            .filter(|test| failures.contains(test))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rerun
+                .iter()
+                .map(|test| description_path(test))
+                .collect::<Vec<_>>(),
+            ["parser / parses", "server / starts", "server / stops"]
+        );
+
+        // Tests that are ignored or not run keep failing until they pass
+        let results = [Status::Passed, Status::Ignored].map(result);
+        failures.update(&rerun[..2], &results).unwrap();
+
+        let failures = Failures::read(Some(file.clone()));
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "server / starts\nserver / stops\n"
+        );
+        assert!(!failures.contains(tests[0]) && failures.contains(tests[2]));
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn tells_descriptions_containing_the_separator_apart() {
+        let file = env::temp_dir().join(format!(
+            "demonstrate-{}-separator.failures",
+            std::process::id()
+        ));
+        let tests = [
+            test_case(&["parses a / b", "fractions"]),
+            test_case(&["parses a", "b / fractions"]),
+            test_case(&["parses a", "b", "fractions"]),
+        ];
+        let tests = tests.iter().collect::<Vec<_>>();
+        let results = [Status::Failed, Status::Passed, Status::Passed]
+            .map(|status| TestResult::new(status, None));
+
+        Failures::read(Some(file.clone()))
+            .update(&tests, &results)
+            .unwrap();
+
+        let failures = Failures::read(Some(file.clone()));
+        assert_eq!(
+            tests
+                .iter()
+                .map(|test| failures.contains(test))
+                .collect::<Vec<_>>(),
+            [true, false, false]
+        );
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "parses a \\/ b / fractions\n"
+        );
+        fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+//! Runs tests in child processes of the test binary, so that their output can be captured and
+//! they can end their process
+
+use super::{execute_in_thread, Hook, HookTiming, Status, TestCase, TestResult};
+use std::env;
+use std::io::{self, Write};
+use std::process::{self, Command};
+use std::time::Duration;
+
+/// The environment variable that tells a child process which test to run
+const CHILD_TEST: &str = "__DEMONSTRATE_CHILD_TEST";
+
+/// Separates a child's output from the result of its test on its stderr
+///
+/// The result is given as a line with the test's status, such as `status passed`, and a line
+/// for each hook's timing, such as `before 0 1500` for a `before` hook at depth 0 that took 1500
+/// nanoseconds, followed by `message` and the message of the test's result.
+const RESULT_SEPARATOR: &str = "\n__demonstrate_result__\n";
+
+/// The path of the test that this process was spawned to run, if it's a child process
+pub(crate) fn child_test() -> Option<String> {
+    env::var(CHILD_TEST).ok()
+}
+
+/// Whether this process was spawned to run a single test
+pub(crate) fn is_child() -> bool {
+    env::var_os(CHILD_TEST).is_some()
+}
+
+/// The command that spawns a child process of the test binary to run the test with the given path
+pub(crate) fn command(test_path: &str) -> io::Result<Command> {
+    let mut command = Command::new(env::current_exe()?);
+    command.env(CHILD_TEST, test_path);
+    Ok(command)
+}
+
+/// Runs a test in a child process, capturing its stdout and stderr
+pub(crate) fn execute(test: &'static TestCase) -> TestResult {
+    let output = command(&test.test_path()).and_then(|mut command| command.output());
+    let output = match output {
+        Ok(output) => output,
+        Err(error) => {
+            return TestResult::new(
+                Status::Failed,
+                Some(format!("Failed spawning a process for the test: {}", error)),
+            )
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let (stderr, result) = split_result(&stderr);
+
+    let mut result = result.unwrap_or_else(|| {
+        TestResult::new(
+            Status::Failed,
+            Some(format!(
+                "test process exited before reporting a result: {}",
+                output.status
+            )),
+        )
+    });
+    result.output = Some(format!("{}{}", stdout, stderr));
+    result
+}
+
+/// Splits the stderr of a child process into its output and the result that it reported for its
+/// test, if it reported one before exiting
+pub(crate) fn split_result(stderr: &str) -> (&str, Option<TestResult>) {
+    match stderr.rsplit_once(RESULT_SEPARATOR) {
+        Some((stderr, trailer)) => (stderr, Some(parse_result(trailer))),
+        None => (stderr, None),
+    }
+}
+
+/// Parses the result that a child process reported for its test
+fn parse_result(trailer: &str) -> TestResult {
+    let mut status = Status::Failed;
+    let mut hooks = Vec::new();
+    let mut lines = trailer.split_inclusive('\n');
+    for line in lines.by_ref() {
+        match line.trim_end().split(' ').collect::<Vec<_>>()[..] {
+            ["message"] => break,
+            ["status", name] => status = Status::from_name(name).unwrap_or(Status::Failed),
+            [hook, depth, nanos] => hooks.extend(parse_hook(hook, depth, nanos)),
+            _ => {}
+        }
+    }
+    let message = Some(lines.collect::<String>()).filter(|message| !message.is_empty());
+
+    let mut result = TestResult::new(status, message);
+    result.hooks = hooks;
+    result
+}
+
+fn parse_hook(hook: &str, depth: &str, nanos: &str) -> Option<HookTiming> {
+    Some(HookTiming {
+        depth: depth.parse().ok()?,
+        hook: match hook {
+            "before" => Hook::Before,
+            "after" => Hook::After,
+            _ => return None,
+        },
+        duration: Duration::from_nanos(nanos.parse().ok()?),
+    })
+}
+
+/// Runs the test that this child process was spawned for, exiting with a failure if it fails
+pub(crate) fn run_child(test_path: &str) {
+    let Some(test) = inventory::iter::<TestCase>().find(|test| test.test_path() == test_path)
+    else {
+        eprintln!("error: no test named '{}'", test_path);
+        process::exit(101);
+    };
+
+    super::capture_panic_messages();
+    let result = execute_in_thread(test);
+    report_result(&result);
+    if result.status == Status::Failed {
+        process::exit(101);
+    }
+}
+
+/// Reports the result of the test that this child process was spawned for on its stderr
+pub(crate) fn report_result(result: &TestResult) {
+    let mut trailer = String::from(RESULT_SEPARATOR);
+    trailer.push_str(&format!("status {}\n", result.status.name()));
+    for timing in &result.hooks {
+        let hook = match timing.hook {
+            Hook::Before => "before",
+            Hook::After => "after",
+        };
+        let nanos = timing.duration.as_nanos();
+        trailer.push_str(&format!("{} {} {}\n", hook, timing.depth, nanos));
+    }
+    trailer.push_str("message\n");
+
+    trailer.push_str(result.message.as_deref().unwrap_or_default());
+
+    let _ = io::stdout().flush();
+    eprint!("{}", trailer);
+}
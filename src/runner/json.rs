@@ -0,0 +1,144 @@
+//! Reports tests as JSON lines, with an event for the start and end of the run and for each test
+
+use super::reporter::written;
+use super::{Reporter, Status, TestCase, TestResult};
+use std::io::Write;
+use std::time::Duration;
+
+/// Writes a JSON object on its own line for each event of the run
+pub(crate) struct Json<W> {
+    out: W,
+}
+
+impl<W: Write> Json<W> {
+    pub(crate) fn new(out: W) -> Self {
+        Json { out }
+    }
+}
+
+impl<W: Write> Reporter for Json<W> {
+    fn run_started(&mut self, tests: &[&'static TestCase]) {
+        written(writeln!(
+            self.out,
+            r#"{{"type": "run", "event": "started", "test_count": {}}}"#,
+            tests.len()
+        ));
+    }
+
+    fn test_finished(&mut self, test: &'static TestCase, result: &TestResult) {
+        let path = test
+            .path
+            .iter()
+            .map(|name| string(name))
+            .collect::<Vec<_>>();
+        written(writeln!(
+            self.out,
+            r#"{{"type": "test", "event": "finished", "status": "{}", "description": [{}], "test_path": {}, "file": {}, "line": {}, "column": {}, "duration": {:.6}, "message": {}, "output": {}}}"#,
+            result.status.name(),
+            path.join(", "),
+            string(&test.test_path()),
+            string(test.file),
+            test.line,
+            test.column,
+            result.duration.as_secs_f64(),
+            result.message.as_deref().map_or("null".to_owned(), string),
+            result.output.as_deref().map_or("null".to_owned(), string),
+        ));
+    }
+
+    fn run_finished(
+        &mut self,
+        _tests: &[&'static TestCase],
+        results: &[TestResult],
+        elapsed: Duration,
+    ) {
+        let count = |status| {
+            results
+                .iter()
+                .filter(|result| result.status == status)
+                .count()
+        };
+        let event = if count(Status::Failed) > 0 {
+            "failed"
+        } else {
+            "ok"
+        };
+        written(writeln!(
+            self.out,
+            r#"{{"type": "run", "event": "{}", "passed": {}, "failed": {}, "ignored": {}, "skipped": {}, "xfailed": {}, "duration": {:.6}}}"#,
+            event,
+            count(Status::Passed),
+            count(Status::Failed),
+            count(Status::Ignored),
+            count(Status::Skipped),
+            count(Status::ExpectedFailure),
+            elapsed.as_secs_f64(),
+        ));
+        written(self.out.flush());
+    }
+}
+
+/// Formats text as a JSON string, escaping it the same way as the manifests exported by
+/// `demonstrate!`
+fn string(text: &str) -> String {
+    let mut string = String::with_capacity(text.len() + 2);
+    string.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            c if c.is_control() => string.push_str(&format!("\\u{:04x}", c as u32)),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::test_case;
+
+    #[test]
+    fn escapes_strings_like_the_manifest() {
+        assert_eq!(
+            string("\"4\" \\ 5\n\r\t\u{1}\u{7f}é"),
+            r#""\"4\" \\ 5\n\r\t\u0001\u007fé""#
+        );
+    }
+
+    #[test]
+    fn writes_an_escaped_object_per_event() {
+        let tests: [&'static TestCase; 2] = [
+            Box::leak(Box::new(test_case(&["parser", "parses \"4\""]))),
+            Box::leak(Box::new(test_case(&["parser", "rejects"]))),
+        ];
+        let mut failed = TestResult::new(Status::Failed, Some("a\\b\n\tc\u{1}".to_owned()));
+        failed.duration = Duration::from_millis(5);
+        failed.output = Some("line\r\n".to_owned());
+        let results = [TestResult::new(Status::Passed, None), failed];
+
+        let mut json = Json::new(Vec::new());
+        json.run_started(&tests);
+        for (test, result) in tests.iter().zip(&results) {
+            json.test_finished(test, result);
+        }
+        json.run_finished(&tests, &results, Duration::from_millis(10));
+
+        let lines = String::from_utf8(json.out).unwrap();
+        let lines = lines.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                r#"{"type": "run", "event": "started", "test_count": 2}"#,
+                r#"{"type": "test", "event": "finished", "status": "passed", "description": ["parser", "parses \"4\""], "test_path": "parser::parses \"4\"", "file": "tests/spec.rs", "line": 1, "column": 1, "duration": 0.000000, "message": null, "output": null}"#,
+                r#"{"type": "test", "event": "finished", "status": "failed", "description": ["parser", "rejects"], "test_path": "parser::rejects", "file": "tests/spec.rs", "line": 1, "column": 1, "duration": 0.005000, "message": "a\\b\n\tc\u0001", "output": "line\r\n"}"#,
+                r#"{"type": "run", "event": "failed", "passed": 1, "failed": 1, "ignored": 0, "skipped": 0, "xfailed": 0, "duration": 0.010000}"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Reports tests as JUnit XML, with a test suite for each group of tests sharing a describe path
+
+use super::reporter::written;
+use super::{Reporter, Status, TestCase, TestResult};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Writes a JUnit XML document once every test has finished
+pub(crate) struct JUnit<W> {
+    out: W,
+}
+
+impl<W: Write> JUnit<W> {
+    pub(crate) fn new(out: W) -> Self {
+        JUnit { out }
+    }
+
+    fn write_document(
+        &mut self,
+        tests: &[&'static TestCase],
+        results: &[TestResult],
+        elapsed: Duration,
+    ) -> io::Result<()> {
+        let out = &mut self.out;
+        // Tests are grouped by their describe path, in the order the paths first appear
+        let mut suites: Vec<(&[&str], Vec<Case>)> = Vec::new();
+        for (test, result) in tests.iter().zip(results) {
+            match suites
+                .iter_mut()
+                .find(|(groups, _)| *groups == test.groups())
+            {
+                Some((_, cases)) => cases.push((test, result)),
+                None => suites.push((test.groups(), vec![(test, result)])),
+            }
+        }
+
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<testsuites tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+            results.len(),
+            count(results.iter(), &[Status::Failed]),
+            count(results.iter(), SKIPPED),
+            elapsed.as_secs_f64(),
+        )?;
+        for (groups, cases) in &suites {
+            let time = cases
+                .iter()
+                .map(|(_, result)| result.duration)
+                .sum::<Duration>();
+            writeln!(
+                out,
+                r#"  <testsuite name="{}" tests="{}" failures="{}" skipped="{}" time="{:.3}">"#,
+                escape(&groups.join(" / ")),
+                cases.len(),
+                count(cases.iter().map(|(_, result)| *result), &[Status::Failed]),
+                count(cases.iter().map(|(_, result)| *result), SKIPPED),
+                time.as_secs_f64(),
+            )?;
+            for (test, result) in cases {
+                testcase(out, test, result)?;
+            }
+            writeln!(out, "  </testsuite>")?;
+        }
+        writeln!(out, "</testsuites>")?;
+        out.flush()
+    }
+}
+
+impl<W: Write> Reporter for JUnit<W> {
+    fn test_finished(&mut self, _test: &'static TestCase, _result: &TestResult) {}
+
+    fn run_finished(
+        &mut self,
+        tests: &[&'static TestCase],
+        results: &[TestResult],
+        elapsed: Duration,
+    ) {
+        written(self.write_document(tests, results, elapsed));
+    }
+}
+
+/// A test along with its result
+type Case<'a> = (&'a TestCase, &'a TestResult);
+
+/// Writes the `testcase` element of a test
+fn testcase(out: &mut impl Write, test: &TestCase, result: &TestResult) -> io::Result<()> {
+    let test_path = test.test_path();
+    let classname = test_path
+        .rsplit_once("::")
+        .map_or("", |(module_path, _)| module_path);
+    write!(
+        out,
+        r#"    <testcase name="{}" classname="{}" file="{}" line="{}" time="{:.3}""#,
+        escape(test.path.last().unwrap()),
+        escape(classname),
+        escape(test.file),
+        test.line,
+        result.duration.as_secs_f64(),
+    )?;
+
+    let output = result.output.as_deref().filter(|output| !output.is_empty());
+    if result.status == Status::Passed && output.is_none() {
+        return writeln!(out, "/>");
+    }
+
+    writeln!(out, ">")?;
+    match result.status {
+        Status::Failed => {
+            let message = result.message.as_deref().unwrap_or_default();
+            writeln!(
+                out,
+                r#"      <failure message="{}">{}</failure>"#,
+                escape(message.lines().next().unwrap_or_default()),
+                escape(message),
+            )?;
+        }
+        Status::Ignored => writeln!(out, "      <skipped/>")?,
+        Status::Skipped => writeln!(
+            out,
+            r#"      <skipped message="{}"/>"#,
+            escape(result.message.as_deref().unwrap_or_default())
+        )?,
+        // JUnit has no notion of expected failures, so they're reported as skipped like pytest
+        // does
+        Status::ExpectedFailure => writeln!(
+            out,
+            r#"      <skipped message="expected failure: {}"/>"#,
+            escape(test.xfail.unwrap_or_default())
+        )?,
+        _ => {}
+    }
+    if let Some(output) = output {
+        writeln!(out, "      <system-out>{}</system-out>", escape(output))?;
+    }
+    writeln!(out, "    </testcase>")
+}
+
+/// The statuses of tests that JUnit considers to be skipped
+const SKIPPED: &[Status] = &[Status::Ignored, Status::Skipped, Status::ExpectedFailure];
+
+fn count<'a>(results: impl Iterator<Item = &'a TestResult>, statuses: &[Status]) -> usize {
+    results
+        .filter(|result| statuses.contains(&result.status))
+        .count()
+}
+
+/// Escapes text for an XML attribute or element
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML documents
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::test_case;
+
+    #[test]
+    fn writes_a_suite_per_describe_path() {
+        let tests: [&'static TestCase; 3] = [
+            Box::leak(Box::new(test_case(&["parser", "parses <4> & \"5\""]))),
+            Box::leak(Box::new(test_case(&["server", "starts"]))),
+            Box::leak(Box::new(test_case(&["parser", "rejects"]))),
+        ];
+        let mut failed =
+            TestResult::new(Status::Failed, Some("'a' < b\u{1}\nat line 2".to_owned()));
+        failed.output = Some("printed & logged".to_owned());
+        let results = [
+            TestResult::new(Status::Passed, None),
+            TestResult::new(Status::Skipped, Some("no \"server\"".to_owned())),
+            failed,
+        ];
+
+        let mut junit = JUnit::new(Vec::new());
+        junit.run_finished(&tests, &results, Duration::from_millis(10));
+        assert_eq!(
+            String::from_utf8(junit.out).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites tests="3" failures="1" skipped="1" time="0.010">
+  <testsuite name="parser" tests="2" failures="1" skipped="0" time="0.000">
+    <testcase name="parses &lt;4&gt; &amp; &quot;5&quot;" classname="parser" file="tests/spec.rs" line="1" time="0.000"/>
+    <testcase name="rejects" classname="parser" file="tests/spec.rs" line="1" time="0.000">
+      <failure message="&apos;a&apos; &lt; b">&apos;a&apos; &lt; b
+at line 2</failure>
+      <system-out>printed &amp; logged</system-out>
+    </testcase>
+  </testsuite>
+  <testsuite name="server" tests="1" failures="0" skipped="1" time="0.000">
+    <testcase name="starts" classname="server" file="tests/spec.rs" line="1" time="0.000">
+      <skipped message="no &quot;server&quot;"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
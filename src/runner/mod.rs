@@ -0,0 +1,513 @@
+//! The test runner used by `demonstrate::main!()`, which runs the tests registered by the
+//! `demonstrate!` macro and reports them as a tree of their descriptions, or in the format of a
+//! [`Reporter`](trait.Reporter.html)
+
+use crate::bench;
+use crate::resource::Users;
+use crate::serial::serial;
+use crate::shared::Leased;
+use crate::skip::skipped_reason;
+use durations::Durations;
+use failures::Failures;
+use options::Options;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub(crate) use isolate::{command as child_command, is_child, report_result, split_result};
+pub use profile::{Hook, HookTimer, HookTiming, HookTimings};
+pub use reporter::{Reporter, ReporterFactory};
+
+mod documentation;
+mod durations;
+mod failures;
+mod isolate;
+mod json;
+mod junit;
+mod options;
+mod order;
+mod profile;
+mod reporter;
+mod shard;
+mod tap;
+
+/// A test registered by an `it`/`test` block
+pub struct TestCase {
+    /// The names of the test's ancestoral blocks followed by its own
+    pub path: &'static [&'static str],
+    /// The module path of the generated test function
+    pub module_path: &'static str,
+    /// The name of the generated test function
+    pub name: &'static str,
+    /// The source file that the test was declared in
+    pub file: &'static str,
+    /// The line that the test's description is on
+    pub line: u32,
+    /// The column that the test's description starts at
+    pub column: u32,
+    /// Whether the test was marked with `#[ignore]`
+    pub ignored: bool,
+    /// Whether the test was marked with `#[should_panic]`
+    pub should_panic: ShouldPanic,
+    /// Why the test is expected to fail, if it was marked with `xfail ".."`
+    pub xfail: Option<&'static str>,
+    /// The sorted keys of the locks declared with `#[serial]` that the test holds while running
+    pub serial: &'static [&'static str],
+    /// The resources declared with `#[resource(..)]` that the test leases
+    pub resources: &'static [fn() -> &'static dyn Users],
+    /// The states of ancestoral `before_all` blocks and describe-scoped fixtures that the test
+    /// leases, which death tests leave to their child processes
+    pub shared: &'static [fn() -> &'static dyn Leased],
+    /// Calls the generated test function
+    pub run: fn() -> Result<(), String>,
+}
+
+inventory::collect!(TestCase);
+
+impl TestCase {
+    /// The descriptions of the test's ancestoral blocks
+    pub fn groups(&self) -> &'static [&'static str] {
+        &self.path[..self.path.len() - 1]
+    }
+
+    /// The path of the test as it would be named by the libtest harness, without the crate name
+    pub fn test_path(&self) -> String {
+        test_path(self.module_path, self.name)
+    }
+}
+
+/// The path of the test generated as a function with the given name in the given module, as it
+/// would be named by the libtest harness
+pub(crate) fn test_path(module_path: &str, name: &str) -> String {
+    match module_path.split_once("::") {
+        Some((_crate_name, module_path)) => format!("{}::{}", module_path, name),
+        None => name.to_owned(),
+    }
+}
+
+/// Whether a test is expected to panic
+pub enum ShouldPanic {
+    No,
+    Yes,
+    /// The test is expected to panic with a message containing this string
+    WithMessage(&'static str),
+}
+
+/// Converts the value returned by a test into whether it succeeded, like
+/// `std::process::Termination` does for the libtest harness
+pub trait Report {
+    fn report(self) -> Result<(), String>;
+}
+
+impl Report for () {
+    fn report(self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl<T, E: fmt::Debug> Report for Result<T, E> {
+    fn report(self) -> Result<(), String> {
+        self.map(|_| ())
+            .map_err(|error| format!("Error: {:?}", error))
+    }
+}
+
+/// The status of a test once it has finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    Passed,
+    Failed,
+    Ignored,
+    /// The test was skipped at runtime, as an assumption it depends on didn't hold
+    Skipped,
+    /// The test was marked with `xfail ".."` and failed as expected
+    ExpectedFailure,
+}
+
+impl Status {
+    const ALL: [Status; 5] = [
+        Status::Passed,
+        Status::Failed,
+        Status::Ignored,
+        Status::Skipped,
+        Status::ExpectedFailure,
+    ];
+
+    /// The name of the status in lowercase
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Passed => "passed",
+            Status::Failed => "failed",
+            Status::Ignored => "ignored",
+            Status::Skipped => "skipped",
+            Status::ExpectedFailure => "xfailed",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Status::ALL
+            .iter()
+            .copied()
+            .find(|status| status.name() == name)
+    }
+}
+
+/// The result of running a test
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TestResult {
+    pub status: Status,
+    /// How long the test took to run
+    pub duration: Duration,
+    /// Why the test failed or was skipped
+    pub message: Option<String>,
+    /// What the test printed to stdout and stderr, which is only captured when the tests are
+    /// isolated in their own processes with `--isolate`
+    pub output: Option<String>,
+    /// How long each of the `before` and `after` hooks executed for the test took
+    pub hooks: Vec<HookTiming>,
+}
+
+impl TestResult {
+    pub(crate) fn new(status: Status, message: Option<String>) -> Self {
+        TestResult {
+            status,
+            duration: Duration::ZERO,
+            message,
+            output: None,
+            hooks: Vec::new(),
+        }
+    }
+}
+
+thread_local! {
+    /// Whether the current thread is running a test, so its panic messages should be captured
+    static CAPTURING: Cell<bool> = const { Cell::new(false) };
+    /// The message of the last panic on the current thread while it was running a test
+    static PANIC_MESSAGE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Runs the registered tests that match the command line arguments, exiting the process with a
+/// failure if any of them fail
+///
+/// The tests are reported by the reporter of the format selected by the `--format` flag or the
+/// `DEMONSTRATE_FORMAT` environment variable, which is looked up in `reporters` before the
+/// built-in formats. The machine-readable formats are written to the file given by the
+/// `--report-file` flag or the `DEMONSTRATE_REPORT_FILE` environment variable instead of stdout,
+/// if any. The benchmarks registered by `bench` blocks are run instead when the
+/// `--bench` flag is passed.
+pub fn main(reporters: &[(&str, ReporterFactory)]) {
+    if let Some(test_path) = isolate::child_test() {
+        return isolate::run_child(&test_path);
+    }
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "--bench") {
+        return bench::main();
+    }
+
+    let exit = |error: String| -> ! {
+        eprintln!("error: {}", error);
+        process::exit(101);
+    };
+    let options = Options::parse(&args).unwrap_or_else(|error| exit(error));
+    let mut reporter = reporter::select(&options.format, options.report_file.as_deref(), reporters)
+        .unwrap_or_else(|error| exit(error));
+
+    let mut failures = Failures::load();
+    let mut durations = Durations::load();
+    if options.only_failures && failures.is_empty() {
+        eprintln!("No failures were recorded by the previous run");
+    }
+
+    let mut tests = inventory::iter::<TestCase>()
+        .filter(|test| options.matches(test))
+        .filter(|test| !options.only_failures || failures.contains(test))
+        .collect::<Vec<_>>();
+    if let Some(shard) = options.shard {
+        let total = tests.len();
+        shard::select(&mut tests, shard, options.balance, &durations);
+        eprintln!(
+            "Running {} of the {} tests in shard {}",
+            tests.len(),
+            total,
+            shard
+        );
+    }
+    tests.sort_by_key(|test| (test.file, test.line, test.column));
+    if let Some(seed) = options.shuffle_seed {
+        order::shuffle(&mut tests, seed);
+        // The seed is printed to stderr so that it doesn't interfere with the reporters' output
+        eprintln!("Randomized with seed {}", seed);
+    }
+
+    if options.list {
+        for test in &tests {
+            println!("{}: test", test.test_path());
+        }
+        return;
+    }
+
+    capture_panic_messages();
+
+    // Resources are torn down once the last of the tests that will be run using them finishes
+    for test in tests.iter().filter(|test| will_run(test, &options)) {
+        for resource in test.resources {
+            resource().expect_user();
+        }
+    }
+
+    let start = Instant::now();
+    reporter.run_started(&tests);
+    let results = run(&tests, &options, |test, result| {
+        reporter.test_finished(test, result)
+    });
+    for test in tests[results.len()..]
+        .iter()
+        .filter(|test| will_run(test, &options))
+    {
+        finish_resources(test);
+    }
+    let tests = &tests[..results.len()];
+    reporter.run_finished(tests, &results, start.elapsed());
+
+    if let Some(count) = options.profile {
+        profile::print(count, tests, &results, start.elapsed());
+    }
+
+    if let Err(error) = failures.update(tests, &results) {
+        eprintln!("warning: failed persisting the failed tests: {}", error);
+    }
+    if let Err(error) = durations.update(tests, &results) {
+        eprintln!(
+            "warning: failed persisting the durations of the tests: {}",
+            error
+        );
+    }
+
+    if results.iter().any(|result| result.status == Status::Failed) {
+        process::exit(101);
+    }
+}
+
+/// Runs the given tests on `options.test_threads` threads, calling `finished` for each of them in
+/// the order they were given
+///
+/// With `--next-failure`, the tests after the first one that fails are not reported, and no more
+/// are started.
+fn run(
+    tests: &[&'static TestCase],
+    options: &Options,
+    mut finished: impl FnMut(&'static TestCase, &TestResult),
+) -> Vec<TestResult> {
+    let (sender, receiver) = mpsc::channel();
+    let next = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+    let mut results = Vec::with_capacity(tests.len());
+
+    thread::scope(|scope| {
+        for _ in 0..options.test_threads {
+            let sender = sender.clone();
+            let next = &next;
+            let stopped = &stopped;
+            scope.spawn(move || loop {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(test) = tests.get(index) else {
+                    break;
+                };
+                if sender.send((index, execute(test, options))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Results are reported in order, so those that finish early wait for their predecessors
+        let mut pending = BTreeMap::new();
+        'receiving: for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&results.len()) {
+                finished(tests[results.len()], &result);
+                let failed = result.status == Status::Failed;
+                results.push(result);
+                if failed && options.next_failure {
+                    stopped.store(true, Ordering::SeqCst);
+                    break 'receiving;
+                }
+            }
+        }
+    });
+
+    results
+}
+
+/// Whether a test will be run rather than reported as ignored
+fn will_run(test: &TestCase, options: &Options) -> bool {
+    !test.ignored || options.run_ignored
+}
+
+/// Declares that a test has finished with its resources, which are torn down if it was the last
+/// test using them
+fn finish_resources(test: &TestCase) {
+    for resource in test.resources {
+        let resource = resource();
+        if resource.user_finished().is_err() {
+            eprintln!(
+                "warning: the teardown of the `{}` resource panicked",
+                resource.name()
+            );
+        }
+    }
+}
+
+/// Runs a test on its own thread, or in its own process when the tests are isolated
+fn execute(test: &'static TestCase, options: &Options) -> TestResult {
+    if !will_run(test, options) {
+        return TestResult::new(Status::Ignored, None);
+    }
+
+    let start = Instant::now();
+    let mut result = if options.isolate {
+        // The test acquires its locks within its own process, so they're also acquired here to
+        // keep it from running alongside the tests it conflicts with
+        let _serial = serial(test.serial);
+        isolate::execute(test)
+    } else {
+        execute_in_thread(test)
+    };
+    finish_resources(test);
+    result.duration = start.elapsed();
+    result
+}
+
+/// Runs a test on its own thread, so that it is isolated from the other tests
+fn execute_in_thread(test: &'static TestCase) -> TestResult {
+    let (result, panic_message, hooks) = thread::Builder::new()
+        .name(test.test_path())
+        .spawn(move || {
+            CAPTURING.with(|capturing| capturing.set(true));
+            profile::record_hook_timings();
+            let result = panic::catch_unwind(test.run);
+            let panic_message = PANIC_MESSAGE.with(|message| message.take());
+            (result, panic_message, profile::take_hook_timings())
+        })
+        .expect("Failed spawning a thread for the test")
+        .join()
+        .unwrap_or_else(|payload| (Err(payload), None, Vec::new()));
+
+    let (status, message) = match (result, &test.should_panic) {
+        (Err(payload), _) if skipped_reason(&*payload).is_some() => (
+            Status::Skipped,
+            skipped_reason(&*payload).map(str::to_owned),
+        ),
+        (Ok(Ok(())), ShouldPanic::No) => (Status::Passed, None),
+        (Ok(Ok(())), _) => (
+            Status::Failed,
+            Some("test did not panic as expected".to_owned()),
+        ),
+        (Ok(Err(message)), _) => (Status::Failed, Some(message)),
+        (Err(_), ShouldPanic::Yes) => (Status::Passed, None),
+        (Err(payload), should_panic) => {
+            let payload = payload_message(&*payload);
+            let message = panic_message.unwrap_or_else(|| payload.clone());
+            match should_panic {
+                ShouldPanic::WithMessage(expected) if payload.contains(expected) => {
+                    (Status::Passed, None)
+                }
+                ShouldPanic::WithMessage(expected) => (
+                    Status::Failed,
+                    Some(format!(
+                        "{}\npanic did not contain expected string\n      panic message: {:?}\n expected substring: {:?}",
+                        message, payload, expected
+                    )),
+                ),
+                _ => (Status::Failed, Some(message)),
+            }
+        }
+    };
+
+    // A test marked with `xfail` fails the run when it passes, so that the bug it's expected to
+    // fail for is noticed once it's fixed
+    let (status, message) = match (status, test.xfail) {
+        (Status::Failed, Some(_)) => (Status::ExpectedFailure, message),
+        (Status::Passed, Some(reason)) => (
+            Status::Failed,
+            Some(format!("test passed, but was expected to fail: {}", reason)),
+        ),
+        _ => (status, message),
+    };
+
+    let mut result = TestResult::new(status, message);
+    result.hooks = hooks;
+    result
+}
+
+/// Spawns a thread for a hook executed as part of a test, which leaves its panic messages to the
+/// test's result like the test's own thread does
+pub(crate) fn spawn_hook<T: Send + 'static>(
+    hook: impl FnOnce() -> T + Send + 'static,
+) -> thread::JoinHandle<T> {
+    let capturing = CAPTURING.with(Cell::get);
+    thread::spawn(move || {
+        CAPTURING.with(|hook_capturing| hook_capturing.set(capturing));
+        hook()
+    })
+}
+
+/// Replaces the panic hook so that the panic messages of tests are stored with their results
+/// rather than being printed amongst them
+fn capture_panic_messages() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if CAPTURING.with(Cell::get) {
+            let thread = thread::current();
+            let message = format!("thread '{}' {}", thread.name().unwrap_or("<unnamed>"), info);
+            PANIC_MESSAGE.with(|panic_message| *panic_message.borrow_mut() = Some(message));
+        } else {
+            default_hook(info)
+        }
+    }));
+}
+
+/// The message of a panic's payload
+pub(crate) fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
+/// A test declared at the given path of descriptions, for the unit tests of the runner
+#[cfg(test)]
+pub(crate) fn test_case(path: &'static [&'static str]) -> TestCase {
+    let (name, groups) = path.split_last().expect("A test has a description");
+    let module_path = ["spec"].iter().chain(groups).copied().collect::<Vec<_>>();
+    TestCase {
+        path,
+        module_path: Box::leak(module_path.join("::").into_boxed_str()),
+        name,
+        file: "tests/spec.rs",
+        line: 1,
+        column: 1,
+        ignored: false,
+        should_panic: ShouldPanic::No,
+        xfail: None,
+        serial: &[],
+        resources: &[],
+        shared: &[],
+        run: || Ok(()),
+    }
+}
@@ -0,0 +1,233 @@
+//! The command line options of the test runner, which are a subset of those accepted by the
+//! libtest harness
+
+use super::shard::{Balance, Shard};
+use super::{order, TestCase};
+use std::env;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::thread;
+
+/// The options that the test runner was called with
+pub(crate) struct Options {
+    /// Only tests whose paths contain one of these are run, unless there are none
+    filters: Vec<String>,
+    /// Tests whose paths contain one of these are not run
+    skip: Vec<String>,
+    /// Whether the filters must match test paths exactly
+    exact: bool,
+    /// Whether only ignored tests are run
+    ignored_only: bool,
+    /// Whether ignored tests are run
+    pub(crate) run_ignored: bool,
+    /// Whether the tests are listed instead of being run
+    pub(crate) list: bool,
+    /// The number of tests that are run in parallel
+    pub(crate) test_threads: usize,
+    /// The name of the format that the tests are reported in
+    pub(crate) format: Option<String>,
+    /// The file that a machine-readable format is written to instead of stdout
+    pub(crate) report_file: Option<PathBuf>,
+    /// Whether each test is run in its own process, capturing its output
+    pub(crate) isolate: bool,
+    /// The seed that the order of the tests is shuffled with, if they're shuffled
+    pub(crate) shuffle_seed: Option<u64>,
+    /// Whether only the tests that failed the last time they were run are run
+    pub(crate) only_failures: bool,
+    /// Whether the run stops at the first test that fails
+    pub(crate) next_failure: bool,
+    /// The shard of the tests that is run, if they're sharded
+    pub(crate) shard: Option<Shard>,
+    /// How the tests are balanced between the shards
+    pub(crate) balance: Balance,
+    /// The number of the slowest tests and blocks to report, if they're reported
+    pub(crate) profile: Option<usize>,
+}
+
+impl Options {
+    pub(crate) fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            filters: Vec::new(),
+            skip: Vec::new(),
+            exact: false,
+            ignored_only: false,
+            run_ignored: false,
+            list: false,
+            test_threads: env::var("RUST_TEST_THREADS")
+                .ok()
+                .and_then(|threads| threads.parse().ok())
+                .or_else(|| thread::available_parallelism().ok().map(NonZeroUsize::get))
+                .unwrap_or(1),
+            format: env::var("DEMONSTRATE_FORMAT").ok(),
+            report_file: env::var_os("DEMONSTRATE_REPORT_FILE").map(PathBuf::from),
+            isolate: env::var_os("DEMONSTRATE_ISOLATE").is_some(),
+            shuffle_seed: match env::var("DEMONSTRATE_SHUFFLE_SEED") {
+                Ok(seed) => Some(parse_seed(&seed)?),
+                Err(_) => env::var_os("DEMONSTRATE_SHUFFLE").map(|_| order::random_seed()),
+            },
+            only_failures: false,
+            next_failure: false,
+            shard: env::var("DEMONSTRATE_SHARD")
+                .ok()
+                .map(|shard| shard.parse())
+                .transpose()?,
+            balance: env::var("DEMONSTRATE_SHARD_BY")
+                .map_or(Ok(Balance::Subtree), |balance| balance.parse())?,
+            // Setting the variable to anything but a count, like `true` or `yes`, profiles 10 tests
+            profile: env::var("DEMONSTRATE_PROFILE")
+                .ok()
+                .map(|count| count.parse().unwrap_or(10)),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // Options can be followed by their value or include it after `=`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next().cloned())
+                    .ok_or_else(|| format!("Argument to option '{}' missing", flag))
+            };
+
+            match flag {
+                "--exact" => options.exact = true,
+                "--ignored" => {
+                    options.ignored_only = true;
+                    options.run_ignored = true;
+                }
+                "--include-ignored" => options.run_ignored = true,
+                "--isolate" => options.isolate = true,
+                // libtest's terse format has no equivalent, so the default format is used
+                "--format" => options.format = Some(value()?).filter(|format| format != "terse"),
+                "--report-file" => options.report_file = Some(value()?.into()),
+                "--only-failures" => options.only_failures = true,
+                "--next-failure" => {
+                    options.only_failures = true;
+                    options.next_failure = true;
+                }
+                "--shard" => options.shard = Some(value()?.parse()?),
+                "--shard-by" => options.balance = value()?.parse()?,
+                // The number of tests to profile is optional, so it can only be given after `=`
+                "--profile" => options.profile = Some(parse_profile(inline_value)?),
+                "--shuffle" => {
+                    options.shuffle_seed.get_or_insert_with(order::random_seed);
+                }
+                "--shuffle-seed" => options.shuffle_seed = Some(parse_seed(&value()?)?),
+                "--list" => options.list = true,
+                "--skip" => options.skip.push(value()?),
+                "--test-threads" => {
+                    options.test_threads = value()?
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|threads| *threads > 0)
+                        .ok_or("argument for --test-threads must be a positive integer")?;
+                }
+                // Output is only captured by isolating tests, so these and the other options of
+                // libtest that the runner doesn't implement are accepted for compatibility
+                "--nocapture"
+                | "--show-output"
+                | "--quiet"
+                | "-q"
+                | "--report-time"
+                | "--ensure-time"
+                | "--force-run-in-process"
+                | "--exclude-should-panic"
+                | "--test"
+                | "--bench" => {}
+                "--color" | "--logfile" | "-Z" => {
+                    value()?;
+                }
+                // Unstable options can also be given as `-Zunstable-options`
+                flag if flag.starts_with("-Z") => {}
+                flag if flag.starts_with('-') => {
+                    return Err(format!("Unrecognized option: '{}'", flag))
+                }
+                filter => options.filters.push(filter.to_owned()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Whether a test should be included in this run
+    pub(crate) fn matches(&self, test: &TestCase) -> bool {
+        let path = test.test_path();
+        let matches = |filter: &String| {
+            if self.exact {
+                path == *filter
+            } else {
+                path.contains(filter.as_str())
+            }
+        };
+
+        (self.filters.is_empty() || self.filters.iter().any(matches))
+            && !self.skip.iter().any(matches)
+            && (!self.ignored_only || test.ignored)
+    }
+}
+
+fn parse_seed(seed: &str) -> Result<u64, String> {
+    seed.parse()
+        .map_err(|_| format!("the shuffle seed must be an integer, not '{}'", seed))
+}
+
+/// Parses the number of tests to profile, which is 10 by default
+fn parse_profile(count: Option<String>) -> Result<usize, String> {
+    match count.as_deref() {
+        None | Some("") => Ok(10),
+        Some(count) => count.parse().map_err(|_| {
+            format!(
+                "the number of tests to profile must be an integer, not '{}'",
+                count
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        Options::parse(&args)
+    }
+
+    #[test]
+    fn ignores_the_libtest_options_it_does_not_implement() {
+        let options = parse(&[
+            "-q",
+            "--quiet",
+            "--nocapture",
+            "--show-output",
+            "--color",
+            "never",
+            "--color=always",
+            "--format=terse",
+            "-Z",
+            "unstable-options",
+            "-Zunstable-options",
+            "--report-time",
+            "--ensure-time",
+            "--logfile",
+            "log.txt",
+            "--test-threads=2",
+            "parser",
+        ])
+        .unwrap();
+
+        assert_eq!(options.filters, ["parser"]);
+        assert_eq!(options.test_threads, 2);
+        assert_eq!(options.format, None);
+    }
+
+    #[test]
+    fn rejects_unrecognized_options() {
+        let error = parse(&["--quiet", "--unknown"]).err().unwrap();
+        assert_eq!(error, "Unrecognized option: '--unknown'");
+    }
+}
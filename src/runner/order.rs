@@ -0,0 +1,116 @@
+//! Shuffles the order that tests are run in, reproducibly for a given seed
+
+use super::TestCase;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Shuffles the tests such that sibling blocks are in a random order while the tests of each
+/// `describe`/`context` block remain together
+///
+/// Each block is given a key by hashing the seed with the block's path, so the same seed always
+/// produces the same order.
+pub(crate) fn shuffle(tests: &mut [&TestCase], seed: u64) {
+    tests.sort_by_cached_key(|test| {
+        let mut hash = mix(seed ^ hash_str(test.file));
+        test.path
+            .iter()
+            .map(|name| {
+                hash = mix(hash ^ hash_str(name));
+                hash
+            })
+            .collect::<Vec<_>>()
+    });
+}
+
+/// Generates a seed from the current time and process
+pub(crate) fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_nanos() as u64);
+    // Seeds are kept short so that they're easy to pass back
+    mix(nanos ^ u64::from(process::id())) % 100_000
+}
+
+/// The FNV-1a hash of a string, which unlike `DefaultHasher` is the same on every Rust version
+pub(crate) fn hash_str(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// The finalizer of SplitMix64, which scrambles the bits of a value
+pub(crate) fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::test_case;
+
+    fn tests() -> Vec<TestCase> {
+        [
+            &["parser", "parses"][..],
+            &["parser", "numbers", "parses digits"],
+            &["parser", "numbers", "rejects letters"],
+            &["parser", "rejects"],
+            &["server", "starts"],
+            &["server", "stops"],
+            &["server", "requests", "get"],
+            &["server", "requests", "post"],
+            &["runs alone"],
+        ]
+        .iter()
+        .copied()
+        .map(test_case)
+        .collect()
+    }
+
+    fn paths(tests: &[&TestCase]) -> Vec<String> {
+        tests.iter().map(|test| test.path.join(" / ")).collect()
+    }
+
+    #[test]
+    fn orders_the_same_for_a_seed() {
+        let tests = tests();
+        let shuffled = |seed| {
+            let mut shuffled = tests.iter().collect::<Vec<_>>();
+            shuffle(&mut shuffled, seed);
+            paths(&shuffled)
+        };
+
+        assert_eq!(shuffled(42), shuffled(42));
+        assert!((0..10).any(|seed| shuffled(seed) != shuffled(42)));
+    }
+
+    #[test]
+    fn keeps_the_tests_of_a_block_together() {
+        let tests = tests();
+        for seed in 0..100 {
+            let mut shuffled = tests.iter().collect::<Vec<_>>();
+            shuffle(&mut shuffled, seed);
+
+            for test in &tests {
+                for depth in 1..test.path.len() {
+                    let group = &test.path[..depth];
+                    let positions = shuffled
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, test)| test.path.starts_with(group))
+                        .map(|(position, _)| position)
+                        .collect::<Vec<_>>();
+                    assert_eq!(
+                        positions.last().unwrap() - positions[0] + 1,
+                        positions.len(),
+                        "{:?} was split up by seed {}: {:?}",
+                        group,
+                        seed,
+                        paths(&shuffled)
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Times the `before` and `after` hooks of tests, and reports the slowest tests and the
+//! `describe`/`context` blocks with the most setup time
+
+use super::{TestCase, TestResult};
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A kind of hook that is executed as part of each test
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hook {
+    Before,
+    After,
+}
+
+/// How long the hook of one of a test's ancestoral blocks took to execute for the test
+#[derive(Clone, Debug)]
+pub struct HookTiming {
+    /// The depth of the block that the hook belongs to, which is the index of its description
+    /// within the test's path
+    pub depth: usize,
+    pub hook: Hook,
+    pub duration: Duration,
+}
+
+/// The timings of the hooks executed for a test
+///
+/// Each test binds these before its first hook, on the thread running it, and its hooks record
+/// into them, so that hooks resumed on other threads, such as the workers of a multi-threaded
+/// async runtime, are still timed for the test.
+#[derive(Clone, Default)]
+pub struct HookTimings(Option<Arc<Mutex<Vec<HookTiming>>>>);
+
+thread_local! {
+    /// The timings of the test that `demonstrate::main!()` is running on the current thread
+    static HOOK_TIMINGS: RefCell<HookTimings> = const { RefCell::new(HookTimings(None)) };
+}
+
+impl HookTimings {
+    /// The timings of the test running on the current thread, which only record anything if the
+    /// test is being run by `demonstrate::main!()`
+    pub fn current() -> Self {
+        HOOK_TIMINGS.with(|timings| timings.borrow().clone())
+    }
+
+    fn push(&self, timing: HookTiming) {
+        if let Some(timings) = &self.0 {
+            timings
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(timing);
+        }
+    }
+}
+
+/// Times a hook that has been inserted into the body of a test
+pub struct HookTimer(Instant);
+
+impl HookTimer {
+    pub fn start() -> Self {
+        HookTimer(Instant::now())
+    }
+
+    /// Records the duration of the hook into the timings of its test
+    pub fn finish(self, timings: &HookTimings, depth: usize, hook: Hook) {
+        timings.push(HookTiming {
+            depth,
+            hook,
+            duration: self.0.elapsed(),
+        });
+    }
+}
+
+/// Starts recording the timings of the hooks executed by the test about to run on the current
+/// thread
+pub(crate) fn record_hook_timings() {
+    HOOK_TIMINGS.with(|timings| timings.replace(HookTimings(Some(Arc::default()))));
+}
+
+/// Takes the timings of the hooks executed by the test that ran on the current thread, wherever
+/// they were executed
+pub(crate) fn take_hook_timings() -> Vec<HookTiming> {
+    HOOK_TIMINGS.with(|timings| match timings.take().0 {
+        Some(timings) => mem::take(&mut *timings.lock().unwrap_or_else(PoisonError::into_inner)),
+        None => Vec::new(),
+    })
+}
+
+/// Prints the `count` slowest tests followed by the `count` blocks whose hooks took the most time
+///
+/// The profile is printed to stderr so that it doesn't interfere with the reporters' output.
+pub(crate) fn print(count: usize, tests: &[&TestCase], results: &[TestResult], elapsed: Duration) {
+    // Like `eprintln!`, failing to print the profile is ignored
+    let _ = write(&mut io::stderr().lock(), count, tests, results, elapsed);
+}
+
+/// Writes the profile printed by `print`
+fn write(
+    out: &mut impl Write,
+    count: usize,
+    tests: &[&TestCase],
+    results: &[TestResult],
+    elapsed: Duration,
+) -> io::Result<()> {
+    let mut slowest = tests.iter().zip(results).collect::<Vec<_>>();
+    slowest.sort_by_key(|(_, result)| Reverse(result.duration));
+    slowest.truncate(count);
+
+    let total = slowest
+        .iter()
+        .map(|(_, result)| result.duration)
+        .sum::<Duration>();
+    writeln!(
+        out,
+        "\nTop {} slowest tests ({:.2} seconds, {:.1}% of total time):",
+        slowest.len(),
+        total.as_secs_f64(),
+        100.0 * total.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON),
+    )?;
+    for (test, result) in &slowest {
+        writeln!(out, "  {}", test.path.join(" / "))?;
+        writeln!(
+            out,
+            "    {:.5} seconds {}:{}:{}",
+            result.duration.as_secs_f64(),
+            test.file,
+            test.line,
+            test.column
+        )?;
+    }
+
+    // The hooks' timings are summed for each block, identified by its file and path
+    let mut blocks = BTreeMap::<_, BlockTime>::new();
+    for (test, result) in tests.iter().zip(results) {
+        for timing in &result.hooks {
+            let Some(path) = test.path.get(..=timing.depth) else {
+                continue;
+            };
+            let block = blocks.entry((test.file, path)).or_default();
+            match timing.hook {
+                Hook::Before => {
+                    block.before += timing.duration;
+                    block.tests += 1;
+                }
+                Hook::After => block.after += timing.duration,
+            }
+        }
+    }
+    if blocks.is_empty() {
+        return Ok(());
+    }
+
+    let mut blocks = blocks.into_iter().collect::<Vec<_>>();
+    blocks.sort_by_key(|(_, time)| Reverse(time.before + time.after));
+    blocks.truncate(count);
+
+    writeln!(
+        out,
+        "\nTop {} describes by cumulative setup time:",
+        blocks.len()
+    )?;
+    for ((_, path), time) in &blocks {
+        writeln!(out, "  {}", path.join(" / "))?;
+        writeln!(
+            out,
+            "    {:.5} seconds in `before` over {} tests, {:.5} seconds in `after`",
+            time.before.as_secs_f64(),
+            time.tests,
+            time.after.as_secs_f64()
+        )?;
+    }
+    Ok(())
+}
+
+/// The cumulative time of a block's hooks
+#[derive(Default)]
+struct BlockTime {
+    before: Duration,
+    after: Duration,
+    /// The number of tests that the `before` hook was executed for
+    tests: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{test_case, Status};
+
+    fn result(millis: u64, hooks: Vec<HookTiming>) -> TestResult {
+        let mut result = TestResult::new(Status::Passed, None);
+        result.duration = Duration::from_millis(millis);
+        result.hooks = hooks;
+        result
+    }
+
+    fn timing(depth: usize, hook: Hook, millis: u64) -> HookTiming {
+        HookTiming {
+            depth,
+            hook,
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn reports_slowest_tests_and_setup_time() {
+        let tests = [
+            test_case(&["parser", "parses"]),
+            test_case(&["parser", "nested", "rejects"]),
+            test_case(&["server", "starts"]),
+        ];
+        let results = [
+            result(
+                20,
+                vec![timing(0, Hook::Before, 5), timing(0, Hook::After, 1)],
+            ),
+            result(
+                50,
+                vec![timing(0, Hook::Before, 5), timing(1, Hook::Before, 30)],
+            ),
+            result(10, Vec::new()),
+        ];
+
+        let mut out = Vec::new();
+        let tests = tests.iter().collect::<Vec<_>>();
+        write(&mut out, 2, &tests, &results, Duration::from_millis(100)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "
+Top 2 slowest tests (0.07 seconds, 70.0% of total time):
+  parser / nested / rejects
+    0.05000 seconds tests/spec.rs:1:1
+  parser / parses
+    0.02000 seconds tests/spec.rs:1:1
+
+Top 2 describes by cumulative setup time:
+  parser / nested
+    0.03000 seconds in `before` over 1 tests, 0.00000 seconds in `after`
+  parser
+    0.01000 seconds in `before` over 2 tests, 0.00100 seconds in `after`
+"
+        );
+    }
+
+    #[test]
+    fn times_hooks_only_when_running_tests() {
+        HookTimer::start().finish(&HookTimings::current(), 0, Hook::Before);
+        assert!(take_hook_timings().is_empty());
+
+        record_hook_timings();
+        HookTimer::start().finish(&HookTimings::current(), 1, Hook::After);
+
+        let timings = take_hook_timings();
+        assert_eq!(timings.len(), 1);
+        assert_eq!((timings[0].depth, timings[0].hook), (1, Hook::After));
+        assert!(take_hook_timings().is_empty());
+    }
+
+    #[test]
+    fn times_hooks_executed_on_other_threads() {
+        record_hook_timings();
+        let timings = HookTimings::current();
+        std::thread::spawn(move || HookTimer::start().finish(&timings, 2, Hook::Before))
+            .join()
+            .unwrap();
+
+        let timings = take_hook_timings();
+        assert_eq!(timings.len(), 1);
+        assert_eq!((timings[0].depth, timings[0].hook), (2, Hook::Before));
+    }
+}
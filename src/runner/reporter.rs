@@ -0,0 +1,123 @@
+//! The reporters of the test runner and the selection of one by its format's name
+
+use super::documentation::Documentation;
+use super::json::Json;
+use super::junit::JUnit;
+use super::tap::Tap;
+use super::{TestCase, TestResult};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Reports the progress and results of the tests run by `demonstrate::main!()`
+///
+/// Additional reporters are made available to the `--format` flag and `DEMONSTRATE_FORMAT`
+/// environment variable by passing them to [`main!()`](macro.main.html) along with the name of
+/// their format.
+/// ```
+/// use demonstrate::{Reporter, Status, TestCase, TestResult};
+///
+/// struct Dots;
+///
+/// impl Reporter for Dots {
+///     fn test_finished(&mut self, _test: &'static TestCase, result: &TestResult) {
+///         print!("{}", if result.status == Status::Failed { "F" } else { "." });
+///     }
+/// }
+///
+/// // demonstrate::main!("dots" => Dots);
+/// ```
+pub trait Reporter {
+    /// Called before any test is run with the tests that will be run, in the order that they will
+    /// be reported
+    fn run_started(&mut self, _tests: &[&'static TestCase]) {}
+
+    /// Called once a test has finished, in the order that the tests were given to `run_started`
+    fn test_finished(&mut self, test: &'static TestCase, result: &TestResult);
+
+    /// Called once every test has finished with their results and the duration of the whole run
+    fn run_finished(
+        &mut self,
+        _tests: &[&'static TestCase],
+        _results: &[TestResult],
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+/// Creates a reporter for the format that it was registered under
+pub type ReporterFactory = fn() -> Box<dyn Reporter>;
+
+/// The formats that are always available, with `documentation` being used by default
+const FORMATS: &[(&str, ReporterFactory)] = &[
+    ("documentation", || Box::new(Documentation::new())),
+    ("pretty", || Box::new(Documentation::new())),
+];
+
+/// Creates a reporter of a machine-readable format that writes to the given output
+type ReportFactory = fn(Box<dyn Write>) -> Box<dyn Reporter>;
+
+/// The machine-readable formats, which can be written to a report file instead of stdout
+const REPORTS: &[(&str, ReportFactory)] = &[
+    ("junit", |out| Box::new(JUnit::new(out))),
+    ("tap", |out| Box::new(Tap::new(out))),
+    ("json", |out| Box::new(Json::new(out))),
+];
+
+/// Creates the reporter of a format, which is looked up in `reporters` before the built-in ones
+///
+/// Only the machine-readable formats can be written to a `report_file`, since the others report
+/// the progress of the run as it happens.
+pub(crate) fn select(
+    format: &Option<String>,
+    report_file: Option<&Path>,
+    reporters: &[(&str, ReporterFactory)],
+) -> Result<Box<dyn Reporter>, String> {
+    let format = format.as_deref().unwrap_or("documentation");
+    if let Some((_, report)) = REPORTS
+        .iter()
+        .find(|(name, _)| *name == format && !reporters.iter().any(|(custom, _)| custom == name))
+    {
+        let out: Box<dyn Write> = match report_file {
+            Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|error| {
+                format!("Failed to create '{}': {}", path.display(), error)
+            })?)),
+            None => Box::new(io::stdout()),
+        };
+        return Ok(report(out));
+    }
+
+    let reporter = reporters
+        .iter()
+        .chain(FORMATS)
+        .find(|(name, _)| *name == format)
+        .map(|(_, reporter)| reporter())
+        .ok_or_else(|| {
+            let names = reporters
+                .iter()
+                .chain(FORMATS)
+                .map(|(name, _)| *name)
+                .chain(REPORTS.iter().map(|(name, _)| *name))
+                .collect::<Vec<_>>();
+            format!(
+                "Unknown format '{}', expected one of: {}",
+                format,
+                names.join(", ")
+            )
+        })?;
+    match report_file {
+        Some(_) => Err(format!(
+            "The '{}' format can't be written to a report file, only junit, tap and json can",
+            format
+        )),
+        None => Ok(reporter),
+    }
+}
+
+/// Panics if a report couldn't be written, as `println!` does when stdout can't be written to
+pub(crate) fn written(result: io::Result<()>) {
+    if let Err(error) = result {
+        panic!("failed writing the report: {}", error)
+    }
+}
@@ -0,0 +1,319 @@
+//! Deterministically partitions the tests into shards, so that they can be run by several machines
+
+use super::durations::Durations;
+use super::order::{hash_str, mix};
+use super::TestCase;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// One of a number of shards, such as `2/5`
+#[derive(Clone, Copy)]
+pub(crate) struct Shard {
+    /// The one-based index of the shard
+    index: usize,
+    count: usize,
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(shard: &str) -> Result<Self, String> {
+        let error = || {
+            format!(
+                "the shard must be given as <index>/<count>, such as 2/5, not '{}'",
+                shard
+            )
+        };
+        let (index, count) = shard.split_once('/').ok_or_else(error)?;
+        let index = index.trim().parse::<usize>().map_err(|_| error())?;
+        let count = count.trim().parse::<usize>().map_err(|_| error())?;
+        if index == 0 || index > count {
+            return Err(format!(
+                "the shard index must be between 1 and {}, not {}",
+                count, index
+            ));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+/// How the tests are balanced between the shards
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Balance {
+    /// The tests of `describe`/`context` blocks are kept together, balancing the number of tests
+    Subtree,
+    /// The tests are balanced by how long they took the last time that they were run
+    Durations,
+}
+
+impl FromStr for Balance {
+    type Err = String;
+
+    fn from_str(balance: &str) -> Result<Self, String> {
+        match balance {
+            "subtree" => Ok(Balance::Subtree),
+            "durations" => Ok(Balance::Durations),
+            _ => Err(format!(
+                "the shards must be balanced by subtree or durations, not '{}'",
+                balance
+            )),
+        }
+    }
+}
+
+/// Keeps the tests of a shard, in their original order
+///
+/// The tests are divided into units which are each assigned to a shard by their description
+/// path. This only depends on the tests and the persisted durations, so every machine given the
+/// same tests and durations agrees on the partition.
+pub(crate) fn select(
+    tests: &mut Vec<&TestCase>,
+    shard: Shard,
+    balance: Balance,
+    durations: &Durations,
+) {
+    // Tests are ordered by their location first, so that the partition doesn't depend on the
+    // order that they were registered in
+    let mut order = (0..tests.len()).collect::<Vec<_>>();
+    order.sort_by_key(|index| {
+        let test = tests[*index];
+        (test.file, test.line, test.column, test.path)
+    });
+    let sorted = order.iter().map(|index| tests[*index]).collect::<Vec<_>>();
+
+    let units = match balance {
+        Balance::Subtree => {
+            let target = (sorted.len() + shard.count - 1) / shard.count;
+            let mut units = Vec::new();
+            subtrees(&sorted, (0..sorted.len()).collect(), 0, target, &mut units);
+            units
+                .into_iter()
+                .map(|unit| (unit.len() as f64, key(&sorted, &unit), unit))
+                .collect()
+        }
+        Balance::Durations => {
+            let recorded = sorted
+                .iter()
+                .filter_map(|test| durations.get(test))
+                .collect::<Vec<_>>();
+            // Tests without a recorded duration are assumed to take the mean duration
+            let mean = if recorded.is_empty() {
+                1.0
+            } else {
+                recorded.iter().sum::<f64>() / recorded.len() as f64
+            };
+            sorted
+                .iter()
+                .enumerate()
+                .map(|(index, test)| {
+                    let weight = durations.get(test).unwrap_or(mean);
+                    (weight, key(&sorted, &[index]), vec![index])
+                })
+                .collect()
+        }
+    };
+
+    let mut selected = vec![false; tests.len()];
+    for (index, assigned) in order.into_iter().zip(partition(units, shard.count)) {
+        selected[index] = assigned == shard.index - 1;
+    }
+    let mut selected = selected.into_iter();
+    tests.retain(|_| selected.next().unwrap());
+}
+
+/// Divides the tests into the largest subtrees that have no more than `target` tests, splitting
+/// the larger ones into their nested blocks
+fn subtrees(
+    tests: &[&TestCase],
+    indices: Vec<usize>,
+    depth: usize,
+    target: usize,
+    units: &mut Vec<Vec<usize>>,
+) {
+    let mut children = BTreeMap::<_, Vec<usize>>::new();
+    for index in indices {
+        let test = tests[index];
+        if test.path.len() == depth + 1 {
+            units.push(vec![index]);
+            continue;
+        }
+        // Root blocks of the same name may be declared in several files
+        let file = if depth == 0 { test.file } else { "" };
+        children
+            .entry((file, test.path[depth]))
+            .or_default()
+            .push(index);
+    }
+
+    for (_, child) in children {
+        if child.len() <= target {
+            units.push(child);
+        } else {
+            subtrees(tests, child, depth + 1, target, units);
+        }
+    }
+}
+
+/// Identifies a unit by the file and descriptions shared by all of its tests
+fn key(tests: &[&TestCase], unit: &[usize]) -> String {
+    let first = tests[unit[0]];
+    let shared = unit.iter().fold(first.path.len(), |shared, index| {
+        first.path[..shared]
+            .iter()
+            .zip(tests[*index].path)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+    format!("{}: {}", first.file, first.path[..shared].join(" / "))
+}
+
+/// How much more than an even share of the weight a shard may be given, so that units can stay
+/// in their preferred shard as tests are added or removed
+const SLACK: f64 = 1.25;
+
+/// Assigns each unit's tests to a shard, returning the zero-based shard of each test
+///
+/// Each unit ranks the shards by hashing its key with each of them, and is assigned, heaviest
+/// first, to the highest ranked shard that it doesn't fill beyond its capacity. Adding or removing
+/// a test then only moves the units that are displaced by the change in weight, rather than
+/// every unit after it.
+fn partition(mut units: Vec<(f64, String, Vec<usize>)>, count: usize) -> Vec<usize> {
+    units.sort_by(|(a_weight, a, _), (b_weight, b, _)| b_weight.total_cmp(a_weight).then(a.cmp(b)));
+
+    let capacity = units.iter().map(|(weight, _, _)| weight).sum::<f64>() / count as f64 * SLACK;
+    let mut weights = vec![0.0_f64; count];
+    let mut assigned = vec![0; units.iter().map(|(_, _, unit)| unit.len()).sum()];
+    for (weight, key, unit) in units {
+        let hash = hash_str(&key);
+        let mut ranked = (0..count).collect::<Vec<_>>();
+        ranked.sort_by_key(|shard| std::cmp::Reverse(mix(hash ^ mix(*shard as u64))));
+
+        // Units too heavy for any shard go to the one with the least weight so far
+        let shard = ranked
+            .into_iter()
+            .find(|shard| weights[*shard] + weight <= capacity)
+            .unwrap_or_else(|| {
+                let (shard, _) = weights
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .unwrap();
+                shard
+            });
+        weights[shard] += weight;
+        for index in unit {
+            assigned[index] = shard;
+        }
+    }
+    assigned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{test_case, Status, TestResult};
+    use std::time::Duration;
+
+    /// Five blocks of eight tests, half of them in a nested block, along with any `extra` tests
+    fn suite(extra: &[&'static str]) -> Vec<TestCase> {
+        let mut paths = Vec::new();
+        for group in ["a", "b", "c", "d", "e"] {
+            for test in ["1", "2", "3", "4"] {
+                paths.push(vec![group, "nested", test]);
+                paths.push(vec![group, test]);
+            }
+        }
+        paths.extend(extra.iter().map(|path| path.split('/').collect()));
+        paths
+            .into_iter()
+            .map(|path| test_case(Box::leak(path.into_boxed_slice())))
+            .collect()
+    }
+
+    /// The description paths of the tests in each shard
+    fn shards(tests: &[&TestCase], count: usize, balance: Balance) -> Vec<Vec<String>> {
+        // Tests named with larger numbers were slower the last time that they were run
+        let mut durations = Durations::read(None);
+        let results = tests
+            .iter()
+            .map(|test| {
+                let mut result = TestResult::new(Status::Passed, None);
+                result.duration = Duration::from_millis(test.name.parse().unwrap());
+                result
+            })
+            .collect::<Vec<_>>();
+        durations.update(tests, &results).unwrap();
+
+        (1..=count)
+            .map(|index| {
+                let mut shard = tests.to_vec();
+                select(&mut shard, Shard { index, count }, balance, &durations);
+                shard.iter().map(|test| test.path.join("/")).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn partitions_every_test_into_one_shard() {
+        let suite = suite(&[]);
+        let tests = suite.iter().collect::<Vec<_>>();
+        let paths = tests
+            .iter()
+            .map(|test| test.path.join("/"))
+            .collect::<Vec<_>>();
+        let mut reversed = tests.clone();
+        reversed.reverse();
+
+        for balance in [Balance::Subtree, Balance::Durations] {
+            for count in 1..=6 {
+                let shards = shards(&tests, count, balance);
+                let mut partitioned = shards.concat();
+                partitioned.sort_by_key(|path| paths.iter().position(|other| other == path));
+                assert_eq!(partitioned, paths, "{} shards", count);
+
+                // Tests are kept in their original order, whatever order they were registered in
+                let mut reversed_shards = self::shards(&reversed, count, balance);
+                reversed_shards.iter_mut().for_each(|shard| shard.reverse());
+                assert_eq!(reversed_shards, shards, "{} shards", count);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_blocks_together() {
+        let suite = suite(&[]);
+        let tests = suite.iter().collect::<Vec<_>>();
+        for shard in shards(&tests, 3, Balance::Subtree) {
+            for group in ["a/", "b/", "c/", "d/", "e/"] {
+                let count = shard.iter().filter(|path| path.starts_with(group)).count();
+                assert!(count == 0 || count == 8, "{} is split: {:?}", group, shard);
+            }
+        }
+    }
+
+    #[test]
+    fn moves_few_tests_when_tests_are_added() {
+        let suite_before = suite(&[]);
+        let suite_after = suite(&["c/5", "f/1"]);
+        let before = suite_before.iter().collect::<Vec<_>>();
+        let after = suite_after.iter().collect::<Vec<_>>();
+
+        for balance in [Balance::Subtree, Balance::Durations] {
+            let before = shards(&before, 3, balance);
+            let after = shards(&after, 3, balance);
+            let moved = before
+                .iter()
+                .zip(&after)
+                .flat_map(|(before, after)| before.iter().filter(move |path| !after.contains(path)))
+                .collect::<Vec<_>>();
+            assert!(moved.len() <= 4, "{:?}", moved);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Reports tests in the Test Anything Protocol, version 13
+
+use super::reporter::written;
+use super::{Reporter, Status, TestCase, TestResult};
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Writes a TAP test line for each test as it finishes, with the failure message and captured
+/// output of failed tests in a YAML diagnostic block
+pub(crate) struct Tap<W> {
+    out: W,
+    /// The number of tests that have been reported so far
+    reported: usize,
+}
+
+impl<W: Write> Tap<W> {
+    pub(crate) fn new(out: W) -> Self {
+        Tap { out, reported: 0 }
+    }
+
+    fn write_test(&mut self, test: &'static TestCase, result: &TestResult) -> io::Result<()> {
+        self.reported += 1;
+        let number = self.reported;
+        let out = &mut self.out;
+
+        // `#` begins a directive, so it is escaped within descriptions
+        let description = test.path.join(" / ").replace('#', "\\#");
+        match result.status {
+            Status::Failed => writeln!(out, "not ok {} - {}", number, description)?,
+            Status::Ignored => writeln!(out, "ok {} - {} # SKIP ignored", number, description)?,
+            Status::Skipped => {
+                let reason = result.message.as_deref().unwrap_or_default();
+                writeln!(out, "ok {} - {} # SKIP {}", number, description, reason)?;
+            }
+            // Failing `TODO` tests are expected to fail, and don't fail the run
+            Status::ExpectedFailure => {
+                let reason = test.xfail.unwrap_or_default();
+                writeln!(out, "not ok {} - {} # TODO {}", number, description, reason)?;
+            }
+            _ => writeln!(out, "ok {} - {}", number, description)?,
+        }
+
+        if result.status != Status::Failed {
+            return Ok(());
+        }
+        writeln!(out, "  ---")?;
+        writeln!(
+            out,
+            "  at: \"{}:{}:{}\"",
+            test.file.replace('"', "\\\""),
+            test.line,
+            test.column
+        )?;
+        writeln!(
+            out,
+            "  duration_ms: {:.3}",
+            result.duration.as_secs_f64() * 1000.0
+        )?;
+        block(out, "message", result.message.as_deref())?;
+        block(out, "output", result.output.as_deref())?;
+        writeln!(out, "  ...")
+    }
+}
+
+impl<W: Write> Reporter for Tap<W> {
+    fn run_started(&mut self, tests: &[&'static TestCase]) {
+        written(writeln!(self.out, "TAP version 13\n1..{}", tests.len()));
+    }
+
+    fn test_finished(&mut self, test: &'static TestCase, result: &TestResult) {
+        written(self.write_test(test, result));
+    }
+
+    fn run_finished(
+        &mut self,
+        _tests: &[&'static TestCase],
+        _results: &[TestResult],
+        _elapsed: Duration,
+    ) {
+        written(self.out.flush());
+    }
+}
+
+/// Writes a YAML literal block scalar, if there is any text
+fn block(out: &mut impl Write, key: &str, text: Option<&str>) -> io::Result<()> {
+    let Some(text) = text.filter(|text| !text.is_empty()) else {
+        return Ok(());
+    };
+    writeln!(out, "  {}: |-", key)?;
+    for line in text.lines() {
+        writeln!(out, "    {}", line)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! Runtime support used by the code generated from the `demonstrate!` macro
+
+use std::future::Future;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+/// Wakes the thread blocked in `block_on` once its future can make progress
+struct Signal {
+    /// Whether the future was woken since it was last polled
+    woken: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl Wake for Signal {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        *self.woken.lock().unwrap() = true;
+        self.condvar.notify_one();
+    }
+}
+
+/// Runs a future to completion on the current thread
+///
+/// This is the dependency-free executor used by tests declared with `async(block_on)`. It only
+/// polls the given future, so futures that rely on a reactor from another runtime (such as
+/// Tokio's timers and sockets) should use that runtime instead.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let signal = Arc::new(Signal {
+        woken: Mutex::new(false),
+        condvar: Condvar::new(),
+    });
+    let waker = Waker::from(signal.clone());
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+
+        let mut woken = signal.woken.lock().unwrap();
+        while !*woken {
+            woken = signal.condvar.wait(woken).unwrap();
+        }
+        *woken = false;
+    }
+}
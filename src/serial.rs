@@ -0,0 +1,38 @@
+//! Process-wide locks that keep tests marked with `#[serial]` from running concurrently
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+/// The locks of the keys that have been used so far, which live for the rest of the process
+static LOCKS: OnceLock<Mutex<HashMap<&'static str, &'static Mutex<()>>>> = OnceLock::new();
+
+/// Holds the locks of a test's serial keys until it is dropped
+pub struct Serial {
+    _guards: Vec<MutexGuard<'static, ()>>,
+}
+
+/// Acquires the locks of the given keys, blocking until no other test holds any of them
+///
+/// The keys must be sorted so that tests acquiring several of them can't deadlock. Locks
+/// poisoned by tests that panicked while holding them are acquired regardless.
+pub fn serial(keys: &[&'static str]) -> Serial {
+    let locks = keys
+        .iter()
+        .map(|key| {
+            let mut locks = LOCKS
+                .get_or_init(Default::default)
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            *locks
+                .entry(key)
+                .or_insert_with(|| Box::leak(Box::new(Mutex::new(()))))
+        })
+        .collect::<Vec<_>>();
+
+    Serial {
+        _guards: locks
+            .into_iter()
+            .map(|lock| lock.lock().unwrap_or_else(PoisonError::into_inner))
+            .collect(),
+    }
+}
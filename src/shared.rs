@@ -0,0 +1,168 @@
+//! State shared by the tests of a `describe`/`context` block through its `before_all` and
+//! `after_all` blocks
+
+use crate::runner::{spawn_hook, TestCase};
+use std::ops::Deref;
+use std::ptr;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::thread;
+
+/// State that is set up for the first test to lease it and torn down once the expected number of
+/// tests have finished with it
+pub struct Shared<T> {
+    inner: Mutex<SharedInner<T>>,
+    /// The number of tests that lease the state, once it has been counted
+    users: OnceLock<usize>,
+}
+
+struct SharedInner<T> {
+    /// The state, if it is currently set up
+    value: Option<Arc<T>>,
+    /// The number of leases that have been dropped since the state was last torn down
+    finished: usize,
+}
+
+impl<T: Send + Sync + 'static> Shared<T> {
+    pub const fn new() -> Self {
+        Shared {
+            inner: Mutex::new(SharedInner {
+                value: None,
+                finished: 0,
+            }),
+            users: OnceLock::new(),
+        }
+    }
+
+    /// The number of tests compiled into the test binary that lease the state and aren't marked
+    /// with `#[ignore]`, which is the `total` that their leases count towards
+    ///
+    /// Tests list the states that they lease when they're registered, so those that `#[cfg]`
+    /// leaves out of the build aren't counted.
+    pub fn users(&'static self) -> usize {
+        *self.users.get_or_init(|| {
+            let state = self as *const Self as *const ();
+            inventory::iter::<TestCase>()
+                .filter(|test| !test.ignored)
+                .filter(|test| {
+                    test.shared
+                        .iter()
+                        .any(|shared| ptr::eq(shared() as *const dyn Leased as *const (), state))
+                })
+                .count()
+        })
+    }
+
+    /// Leases the state, which is set up by the first lease to be set up
+    ///
+    /// A lease counts towards the `total` from when it's taken, even if the test holding it is
+    /// skipped before setting it up. Both `setup` and `teardown` are executed on their own
+    /// threads, so they are free to start runtimes of their own even when called from within an
+    /// async test. `teardown` is executed when the `total`th lease is dropped, or when the only
+    /// lease is dropped in a process that was spawned by the test runner to run a single test.
+    pub fn lease(&'static self, total: usize, setup: fn() -> T, teardown: fn(&T)) -> Lease<T> {
+        let total = if crate::runner::is_child() { 1 } else { total };
+
+        Lease {
+            shared: self,
+            value: OnceLock::new(),
+            total,
+            setup,
+            teardown,
+        }
+    }
+
+    /// The state, setting it up if it isn't already
+    fn set_up(&self, setup: fn() -> T) -> Arc<T> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner
+            .value
+            .get_or_insert_with(|| Arc::new(run_isolated(setup)))
+            .clone()
+    }
+}
+
+impl<T: Send + Sync + 'static> Shared<T> {
+    /// The state leased by the current tests, for setting up state that depends on it
+    ///
+    /// This must only be called while a lease is held and set up, such as by the test that set
+    /// this state up before setting up state depending on it.
+    pub fn current(&self) -> Arc<T> {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner
+            .value
+            .clone()
+            .expect("The state was accessed without being set up")
+    }
+}
+
+/// State shared by tests through a `before_all` block or a describe-scoped fixture, as listed by
+/// the tests that lease it
+pub trait Leased: Sync {}
+
+impl<T: Send + Sync + 'static> Leased for Shared<T> {}
+
+impl<T: Send + Sync + 'static> Default for Shared<T> {
+    fn default() -> Self {
+        Shared::new()
+    }
+}
+
+/// A test's lease of the state set up by a `before_all` block
+pub struct Lease<T: Send + Sync + 'static> {
+    shared: &'static Shared<T>,
+    value: OnceLock<Arc<T>>,
+    total: usize,
+    setup: fn() -> T,
+    teardown: fn(&T),
+}
+
+impl<T: Send + Sync + 'static> Lease<T> {
+    /// Sets the state up if it isn't already, which happens when it's first accessed otherwise
+    pub fn set_up(&self) -> &T {
+        self.value.get_or_init(|| self.shared.set_up(self.setup))
+    }
+}
+
+impl<T: Send + Sync + 'static> Deref for Lease<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.set_up()
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for Lease<T> {
+    fn drop(&mut self) {
+        drop(self.value.take());
+
+        let mut inner = self
+            .shared
+            .inner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        inner.finished += 1;
+        if inner.finished < self.total {
+            return;
+        }
+
+        // The state is set up again for any leases taken after the last expected one
+        inner.finished = 0;
+        if let Some(value) = inner.value.take() {
+            let teardown = self.teardown;
+            let result = spawn_hook(move || teardown(&value)).join();
+
+            // Avoid a double panic when the last test to finish has failed
+            if let (Err(payload), false) = (result, thread::panicking()) {
+                drop(inner);
+                std::panic::resume_unwind(payload);
+            }
+        }
+    }
+}
+
+/// Calls `f` on its own thread, propagating any panic to the current thread
+fn run_isolated<T: Send + 'static>(f: fn() -> T) -> T {
+    spawn_hook(f)
+        .join()
+        .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+}
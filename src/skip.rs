@@ -0,0 +1,67 @@
+//! Skips tests at runtime when the assumptions they depend on don't hold
+
+use std::any::Any;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+/// The payload of the unwinding that skips a test, which holds the reason it was skipped
+pub struct Skipped(pub String);
+
+/// Skips the current test by unwinding its thread, without calling the panic hook
+pub fn skip(reason: String) -> ! {
+    panic::resume_unwind(Box::new(Skipped(reason)))
+}
+
+/// The reason a test was skipped, if the payload of its unwinding is a `Skipped`
+pub(crate) fn skipped_reason(payload: &(dyn Any + Send)) -> Option<&str> {
+    payload
+        .downcast_ref::<Skipped>()
+        .map(|Skipped(reason)| reason.as_str())
+}
+
+/// A value that a test can return when it was skipped, so that the libtest harness reports it as
+/// passing
+pub trait Skippable {
+    fn skipped() -> Self;
+}
+
+impl Skippable for () {
+    fn skipped() -> Self {}
+}
+
+impl<T: Skippable, E> Skippable for Result<T, E> {
+    fn skipped() -> Self {
+        Ok(T::skipped())
+    }
+}
+
+/// Runs a test for the libtest harness, printing the reason it was skipped if it was
+///
+/// The libtest harness has no notion of skipped tests, so skipped tests pass. The reason is
+/// written to stderr directly, as the output of passing tests captured by the harness is hidden.
+pub fn libtest<R: Skippable>(test: fn() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(test)) {
+        Ok(value) => value,
+        Err(payload) => skipped(payload),
+    }
+}
+
+/// Reports a test as skipped if the payload of its unwinding is a `Skipped`, resuming the
+/// unwinding otherwise
+pub(crate) fn skipped<R: Skippable>(payload: Box<dyn Any + Send>) -> R {
+    match skipped_reason(&*payload) {
+        Some(reason) => {
+            note(&format!("was skipped: {}", reason));
+            R::skipped()
+        }
+        None => panic::resume_unwind(payload),
+    }
+}
+
+/// Writes a note about the current test to stderr, such as the reason it was skipped
+pub(crate) fn note(note: &str) {
+    let thread = thread::current();
+    let name = thread.name().unwrap_or("<unnamed>");
+    let _ = writeln!(io::stderr(), "test {} {}", name, note);
+}
@@ -0,0 +1,24 @@
+//! Runs the tests marked with `xfail ".."` for the libtest harness, which are expected to fail
+
+use crate::runner::Report;
+use crate::skip::{note, skipped, skipped_reason, Skippable};
+use std::panic::{self, AssertUnwindSafe};
+
+/// Runs a test that is expected to fail for the libtest harness, passing when it panics or
+/// returns an error and failing when it doesn't
+///
+/// Like skipped tests, tests that fail as expected pass with a note written to stderr, as the
+/// libtest harness has no notion of expected failures.
+pub fn libtest_xfail<R: Skippable + Report>(test: fn() -> R, reason: &str) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(test)) {
+        Ok(value) => {
+            if value.report().is_ok() {
+                panic!("test passed, but was expected to fail: {}", reason)
+            }
+        }
+        Err(payload) if skipped_reason(&*payload).is_some() => return skipped(payload),
+        Err(_) => {}
+    }
+    note(&format!("failed as expected: {}", reason));
+    R::skipped()
+}
@@ -0,0 +1,658 @@
+use demonstrate::{assume, demonstrate};
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+fn is_4() -> u8 {
+    4
+}
+
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a test as running until the returned guard is dropped, failing if another test is
+struct Exclusive;
+
+impl Exclusive {
+    fn run() -> Self {
+        assert_eq!(RUNNING.fetch_add(1, Ordering::SeqCst), 0);
+        thread::sleep(Duration::from_millis(10));
+        Exclusive
+    }
+}
+
+impl Drop for Exclusive {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Panics when dropped, so a test that holds it panics once it has finished
+struct Guard;
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        panic!("dropped the guard")
+    }
+}
+
+/// Checks that the last entry of a log is the expected one when dropped
+struct Expects<'a>(&'a RefCell<Vec<&'static str>>, &'static str);
+
+impl Drop for Expects<'_> {
+    fn drop(&mut self) {
+        assert_eq!(self.0.borrow().last(), Some(&self.1));
+    }
+}
+
+#[derive(Debug)]
+enum ParseError {
+    Empty,
+    Invalid(char),
+}
+
+fn parse(input: &str) -> Result<u32, ParseError> {
+    if input.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    input.chars().try_fold(0, |number, c| {
+        let digit = c.to_digit(10).ok_or(ParseError::Invalid(c))?;
+        Ok(number * 10 + digit)
+    })
+}
+
+static SETUPS: AtomicUsize = AtomicUsize::new(0);
+
+static SERVERS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static TEARDOWNS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+demonstrate! {
+    describe "runner" {
+        use super::*;
+
+        before {
+            let four = is_4();
+        }
+
+        it "passes" {
+            assert_eq!(four, 4)
+        }
+
+        it "returns ok" -> Result<(), String> {
+            if four == 4 {
+                Ok(())
+            } else {
+                Err(format!("{} isn't 4", four))
+            }
+        }
+
+        #[should_panic(expected = "isn't 5")]
+        it "expects a panic" {
+            assert!(four == 5, "{} isn't 5", four)
+        }
+
+        #[ignore]
+        it "is ignored" {
+            assert_eq!(four, 5)
+        }
+
+        it "assumes" {
+            assume!(four == 5, "four isn't 5");
+            unreachable!()
+        }
+
+        it "skips" skip_unless env "DEMONSTRATE_UNSET_VARIABLE" {
+            assert_eq!(four, 5)
+        }
+
+        #[serial(running)]
+        context "serially" {
+            before {
+                let _exclusive = Exclusive::run();
+            }
+
+            it "runs alone" {
+                assert_eq!(four, 4)
+            }
+
+            #[should_panic(expected = "poisons the lock")]
+            it "poisons the lock" {
+                panic!("{} poisons the lock", four)
+            }
+
+            it "runs alone after a panic" {
+                assert_eq!(four, 4)
+            }
+        }
+
+        context "returning from after" -> Result<(), String> {
+            after {
+                if four == 4 {
+                    Ok(())
+                } else {
+                    Err(format!("{} isn't 4", four))
+                }
+            }
+
+            it "returns the value of after" {
+                assert_eq!(four, 4);
+            }
+        }
+
+        async(block_on) context "asynchronous" {
+            it "awaits" {
+                assert_eq!(four, async { is_4() }.await)
+            }
+        }
+
+        #[async_attributes::test]
+        async context "with a test attribute" {
+            it "awaits on its runtime" {
+                assert_eq!(four, async_std::task::spawn(async { is_4() }).await)
+            }
+        }
+    }
+
+    describe "skipped tests" {
+        use super::*;
+
+        before_all {
+            let state: u8 = 4;
+        }
+
+        after_all {
+            panic!("tore down the state of {}", state)
+        }
+
+        it "is skipped" skip_unless env "DEMONSTRATE_UNSET_VARIABLE" {
+            assert_eq!(*state, 4)
+        }
+
+        #[should_panic(expected = "tore down the state of 4")]
+        it "tears the state down once the skipped test has finished" {
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(*state, 4)
+        }
+    }
+
+    describe "partly ignored" {
+        use super::*;
+
+        before_all {
+            let state: u8 = 4;
+        }
+
+        after_all {
+            panic!("tore down the state of {}", state)
+        }
+
+        it "uses the state" {
+            assert_eq!(*state, 4)
+        }
+
+        #[ignore]
+        context "ignored" {
+            #[no_inherit(ignore)]
+            #[should_panic(expected = "tore down the state of 4")]
+            it "tears the state down once it has finished" {
+                thread::sleep(Duration::from_millis(50));
+                assert_eq!(*state, 4)
+            }
+        }
+    }
+
+    describe "partly left out of the build" {
+        before_all {
+            let state: u8 = 4;
+        }
+
+        after_all {
+            panic!("tore down the state of {}", state)
+        }
+
+        #[should_panic(expected = "tore down the state of 4")]
+        it "tears the state down once it has finished" {
+            assert_eq!(*state, 4)
+        }
+
+        #[cfg(any())]
+        it "is left out by its attribute" {
+            assert_eq!(*state, 4)
+        }
+
+        context "left out by an inner attribute" {
+            #![cfg(any())]
+
+            it "is left out" {
+                assert_eq!(*state, 4)
+            }
+        }
+
+        #[module(cfg(any()))]
+        context "left out as a module" {
+            it "is left out" {
+                assert_eq!(*state, 4)
+            }
+        }
+    }
+
+    describe "skipped before setting up" {
+        use std::env;
+
+        before_all {
+            assert!(env::var_os("DEMONSTRATE_UNSET_VARIABLE").is_some());
+        }
+
+        it "is skipped" skip_unless env "DEMONSTRATE_UNSET_VARIABLE" {
+            assert!(true)
+        }
+    }
+
+    describe "resources" {
+        use super::*;
+
+        resource "connections" -> AtomicUsize {
+            SETUPS.fetch_add(1, Ordering::SeqCst);
+            AtomicUsize::new(0)
+        } teardown {
+            assert!(connections.load(Ordering::SeqCst) > 0);
+        }
+
+        resource(pool = 2) "counter" -> u32 {
+            0
+        }
+
+        #[resource(connections)]
+        it "sets up once" {
+            connections.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(SETUPS.load(Ordering::SeqCst), 1)
+        }
+
+        #[resource(counter)]
+        context "pooled" {
+            before {
+                let start = *counter;
+                *counter += 1;
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            it "leases exclusively" {
+                assert_eq!(*counter, start + 1)
+            }
+
+            it "leases exclusively in parallel" {
+                assert_eq!(*counter, start + 1)
+            }
+        }
+    }
+
+    describe "another module" {
+        use super::*;
+
+        #[resource(super::resources::connections)]
+        it "shares the resource" {
+            connections.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(SETUPS.load(Ordering::SeqCst), 1)
+        }
+    }
+
+    describe "process state" with_env { "DEMONSTRATE_LEVEL" => "describe" } {
+        use std::env;
+
+        it "sets variables" {
+            assert_eq!(env::var("DEMONSTRATE_LEVEL").unwrap(), "describe")
+        }
+
+        it "overrides variables" with_env { "DEMONSTRATE_LEVEL" => "test" } {
+            assert_eq!(env::var("DEMONSTRATE_LEVEL").unwrap(), "test")
+        }
+
+        it "changes into a temporary directory" in_tempdir {
+            assert_eq!(env::current_dir().unwrap(), tempdir);
+            std::fs::write("file", "contents").unwrap();
+        }
+    }
+
+    #[serial(env, cwd)]
+    describe "restored process state" {
+        use std::env;
+
+        it "restores variables" {
+            assert!(env::var_os("DEMONSTRATE_LEVEL").is_none())
+        }
+
+        it "restores the working directory" {
+            assert_eq!(env::current_dir().unwrap(), std::path::Path::new(env!("CARGO_MANIFEST_DIR")))
+        }
+    }
+
+    describe "fixtures" {
+        use super::*;
+
+        fixture teardowns -> () {} teardown {
+            TEARDOWNS.with(|teardowns| assert_eq!(*teardowns.borrow(), ["sum", "numbers"]));
+        }
+
+        fixture numbers(_teardowns: teardowns) -> Vec<u32> {
+            vec![1, 2]
+        } teardown {
+            TEARDOWNS.with(|teardowns| teardowns.borrow_mut().push("numbers"));
+        }
+
+        fixture sum(numbers: numbers) -> u32 {
+            numbers.iter().sum()
+        } teardown {
+            TEARDOWNS.with(|teardowns| teardowns.borrow_mut().push("sum"));
+        }
+
+        fixture(describe) server -> AtomicUsize {
+            SERVERS.fetch_add(1, Ordering::SeqCst);
+            AtomicUsize::new(8080)
+        }
+
+        fixture(describe) port(server: server) -> usize {
+            server.load(Ordering::SeqCst)
+        }
+
+        it "sets up dependencies first" (sum: sum) {
+            assert_eq!(*sum, 3)
+        }
+
+        it "tears down in reverse order" (numbers: numbers, sum: sum) {
+            numbers.push(3);
+            assert_eq!(*sum, 3)
+        }
+
+        it "shares describe-scoped fixtures" (port: port) {
+            assert_eq!((*port, SERVERS.load(Ordering::SeqCst)), (8080, 1))
+        }
+
+        context "overriding" (port: port) {
+            fixture numbers -> Vec<u32> {
+                vec![4]
+            }
+
+            it "uses the innermost fixture" (numbers: numbers) {
+                assert_eq!((&numbers[..], *port), (&[4][..], 8080))
+            }
+        }
+    }
+
+    describe "fixtures partly left out of the build" {
+        fixture(describe) state -> u8 {
+            4
+        } teardown {
+            panic!("tore down the fixture of {}", state)
+        }
+
+        #[should_panic(expected = "tore down the fixture of 4")]
+        it "tears the fixture down once it has finished" (state: state) {
+            assert_eq!(*state, 4)
+        }
+
+        #[cfg(any())]
+        it "is left out by its attribute" (state: state) {
+            assert_eq!(*state, 4)
+        }
+
+        context "left out by an inner attribute" (state: state) {
+            #![cfg(any())]
+
+            it "is left out" {
+                assert_eq!(*state, 4)
+            }
+        }
+    }
+
+    describe "context" {
+        state {
+            count: u32 = 1,
+            log: Vec<&'static str> = vec!["describe"],
+        }
+
+        before {
+            ctx.count += 1;
+        }
+
+        after {
+            assert!(ctx.log.contains(&"describe"));
+        }
+
+        it "passes the context" {
+            assert_eq!(ctx.count, 2);
+        }
+
+        context "nested" {
+            state {
+                name: &'static str = "nested",
+            }
+
+            before {
+                ctx.log.push(ctx.name);
+            }
+
+            it "merges the fields" {
+                assert_eq!((ctx.count, &ctx.log[..]), (2, &["describe", "nested"][..]));
+            }
+        }
+    }
+
+    describe "unused setup" {
+        use super::*;
+
+        before {
+            let unused = vec![4];
+            #[lazy]
+            let four = is_4();
+            #[lazy]
+            let evaluated: u8 = panic!("evaluated unused setup");
+            #[lazy]
+            let five = four + 1;
+        }
+
+        it "is removed" {
+            assert!(true)
+        }
+
+        it "is kept when used transitively" {
+            assert_eq!(five, 5)
+        }
+
+        context "holding a guard" {
+            before {
+                let _guard = Guard;
+            }
+
+            #[should_panic(expected = "dropped the guard")]
+            it "keeps the guard until the end of the test" {
+                assert!(true)
+            }
+        }
+    }
+
+    #[should_panic]
+    describe "attribute inheritance" {
+        it "inherits the attribute" {
+            panic!("inherited")
+        }
+
+        #[should_panic]
+        it "declares it again" {
+            panic!("declared again")
+        }
+
+        #[should_panic(expected = "specific")]
+        it "overrides the attribute" {
+            panic!("a specific message")
+        }
+
+        #[no_inherit(should_panic)]
+        it "opts out of the attribute" {
+            assert!(true)
+        }
+
+        #[no_inherit]
+        context "without inheritance" {
+            it "inherits nothing" -> Result<(), String> {
+                Ok(())
+            }
+        }
+    }
+
+    describe "expected outcomes" {
+        use super::*;
+
+        before {
+            let mut log = Vec::new();
+        }
+
+        after {
+            log.push("after");
+            assert_eq!(log, ["test", "after"]);
+        }
+
+        it "rejects empty input" panics_with "empty" {
+            log.push("test");
+            parse("").expect("the input is empty");
+        }
+
+        it "fails to parse" -> Result<u32, ParseError> errs_with ParseError::Invalid('x') {
+            log.push("test");
+            parse("4x")
+        }
+
+        async(block_on) context "asynchronous" {
+            it "rejects empty input" panics_with "empty" {
+                log.push("test");
+                async { panic!("the input is empty") }.await
+            }
+
+            it "fails to parse" -> Result<(), ParseError> errs_with ParseError::Empty {
+                log.push("test");
+                assert_eq!(async { parse("") }.await?, 4);
+                Ok(())
+            }
+        }
+    }
+
+    describe "shared hooks" -> Result<(), String> {
+        use super::*;
+
+        before {
+            let log: RefCell<Vec<&str>> = RefCell::new(Vec::new());
+            let four: u8 = is_4();
+        }
+
+        after {
+            assert_eq!(four, 4);
+            assert_eq!(*log.borrow(), ["test"]);
+            log.borrow_mut().push("after");
+        }
+
+        it "returns the value of the test" {
+            log.borrow_mut().push("test");
+            Ok(())
+        }
+
+        it "runs after once" {
+            log.borrow_mut().push("test");
+            assert!(log.borrow().len() == 1);
+            Ok(())
+        }
+
+        it "returns early" {
+            log.borrow_mut().push("test");
+            let number = parse("4").map_err(|error| format!("{:?}", error))?;
+            assert_eq!(number, u32::from(four));
+            Ok(())
+        }
+
+        it "drops its own bindings after the after block" {
+            log.borrow_mut().push("test");
+            let _expects = Expects(&log, "after");
+            Ok(())
+        }
+
+        context "inferring types" {
+            before {
+                let one = 1;
+                let mut items = Vec::new();
+            }
+
+            it "infers one type" {
+                log.borrow_mut().push("test");
+                items.push(1u8);
+                assert_eq!(items[0] + one, 2u8);
+                Ok(())
+            }
+
+            it "infers another type" {
+                log.borrow_mut().push("test");
+                items.push("s");
+                assert_eq!(one, 1u64);
+                assert_eq!(items, ["s"]);
+                Ok(())
+            }
+        }
+    }
+
+    describe "known bugs" xfail "parsing doesn't handle signs yet" {
+        use super::*;
+
+        it "parses a negative number" {
+            assert_eq!(parse("-4").unwrap(), 4)
+        }
+
+        it "parses a positive number" -> Result<(), ParseError> {
+            assert_eq!(parse("+4")?, 4);
+            Ok(())
+        }
+
+        async(block_on) it "parses asynchronously" {
+            assert_eq!(async { parse("-4") }.await.unwrap(), 4)
+        }
+    }
+
+    describe "death tests" {
+        use std::process;
+
+        before {
+            let config = "verbose = maybe";
+        }
+
+        it "exits on bad config" exits_with 2 stderr_contains "invalid config" {
+            eprintln!("error: invalid config: {}", config);
+            process::exit(2)
+        }
+
+        it "aborts" aborts {
+            process::abort()
+        }
+
+        async(block_on) it "exits asynchronously" exits_with 3 {
+            async { process::exit(3) }.await
+        }
+    }
+
+    /// Is left out of the build along with its tests
+    #[module(cfg(any()))]
+    describe "excluded module" {
+        it "would fail to compile" {
+            does_not_exist()
+        }
+    }
+
+    describe "excluded by inner attribute" {
+        #![cfg(any())]
+
+        it "would fail to compile" {
+            does_not_exist()
+        }
+    }
+}
+
+demonstrate::main!();